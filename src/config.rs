@@ -0,0 +1,494 @@
+use serde::{Serialize, Deserialize};
+use std::collections::BTreeSet;
+
+// --- 0. Constants ---
+pub const BATTERY_FULL_SMARTPHONE: f32 = 1000.0;
+pub const BS_RATIO: f64 = 0.15; // Chance each node is a base station rather than a phone
+pub const BATTERY_BASE_STATION: f32 = 5000.0; // Backup battery behind the solar panel
+pub const HARVEST_BASE_STATION: f32 = 20.0; // Solar energy per step, well above idle drain
+
+pub const COST_IDLE: f32 = 0.5;
+pub const COST_TX: f32 = 5.0;
+pub const COST_RX: f32 = 2.0;
+pub const HELLO_BYTES: u32 = 16; // Control beacon: node id plus a little link state
+pub const PACKET_SIZE_BYTES: u32 = 64; // A short text message
+
+pub const REWARD_RELAY: f32 = 1.0; // Token reward per relay
+pub const INSURANCE_PAYOUT: f32 = 10000.0; // USDC payout
+pub const PREMIUM_PER_RELAY: f32 = 5.0; // USDC set aside into the insurance pool per relay reward
+
+pub const NODE_COUNT: u32 = 60;
+pub const GRID_SIZE: f64 = 200.0; // Square area side, in relative units
+pub const MAX_STEPS: i32 = 40;
+pub const DRAIN_SAFETY_CAP: i32 = 1000; // Hard stop for --run-until-empty
+pub const PACKET_TTL: u32 = 15; // Hops before a packet is dropped
+pub const IMPORTANCE_FLOOR: f64 = 0.1; // Importance below which a stale message is no longer re-sent
+
+pub const DISASTER_STEP: i32 = 20;
+pub const DISASTER_ZONE_Y: f64 = 80.0; // Everything south of this line is destroyed
+
+pub const OSCILLATION_FACTOR: f64 = 2.0; // Hops beyond this multiple of the shortest path = oscillating
+
+pub const SLA_DEADLINE_STEPS: u32 = 5; // "95% of packets within 5 steps"
+pub const SLA_TARGET: f64 = 0.95;
+
+pub const INTERFERENCE_FACTOR: f64 = 0.15; // Range lost per concurrently transmitting neighbor
+pub const ADAPTIVE_INITIAL_SCORE: f64 = 0.5; // Untried links start neutral
+pub const ADAPTIVE_ALPHA: f64 = 0.2; // EMA weight of the newest outcome
+pub const ADAPTIVE_FANOUT: usize = 2; // Best-scoring neighbors always tried
+pub const ADAPTIVE_EXPLORE: f64 = 0.05; // Chance of also trying each other neighbor
+
+pub const SWARM_RELAY_PROB: f64 = 0.05; // Chance a full-battery phone relays in Swarm
+pub const FAIR_LOAD_DECAY: f64 = 0.8; // Share of a node's recent relay load still remembered a step later
+
+pub const INTERFERENCE_MIN_SCALE: f64 = 0.2; // Range never shrinks below this fraction
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum SimMode {
+    Flooding, // Old tech (Benchmark baseline)
+    Swarm,    // New tech (Unicorn)
+    WeightedSplit { k: usize }, // Sample up to k neighbors, weighted by battery and progress to target
+    Adaptive, // Learn per-neighbor success scores from ACKs and prefer the best relays
+    FairSwarm, // Swarm, but phones that relayed a lot recently are asked less often
+}
+
+impl SimMode {
+    // Relay rewards and the insurance oracle only run for the token-incentivised modes
+    pub fn has_tokenomics(&self) -> bool {
+        !matches!(self, SimMode::Flooding)
+    }
+}
+
+// How the destination of the benchmark flow is chosen
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum TargetSelection {
+    LastNode,           // node_count - 1 (legacy behaviour)
+    ById(u32),
+    NearestBaseStation, // Base station closest to the source, outside the disaster zone
+    RandomPair,         // Seeded source and target, never a base station or a disaster victim
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum DistanceMetric {
+    Euclidean,
+    Manhattan, // Grid streets: |dx| + |dy|
+}
+
+// What a holder pays to send one packet to its chosen neighbors
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum TransmissionMode {
+    Broadcast, // One TX reaches every neighbor in range (legacy)
+    Unicast,   // One TX per neighbor actually forwarded to
+}
+
+// Which nodes the disaster at DISASTER_STEP destroys
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum DisasterRegion {
+    South { y_max: f64 },       // Everything south of the line (legacy)
+    Fraction { fraction: f64 }, // Random share of active nodes, reproducible under the seed
+    TargetHubs { count: u32 },  // Targeted attack on the `count` highest-degree live nodes
+    Circle { x: f64, y: f64, radius: f64 }, // Everything within `radius` of an epicentre
+}
+
+impl DisasterRegion {
+    // Is this position inside the affected area?
+    pub fn covers(&self, position: (f64, f64)) -> bool {
+        match self {
+            DisasterRegion::South { y_max } => position.1 < *y_max,
+            DisasterRegion::Circle { x, y, radius } => (position.0 - x).hypot(position.1 - y) <= *radius,
+            // Scattered damage: the whole map is at risk
+            DisasterRegion::Fraction { .. } | DisasterRegion::TargetHubs { .. } => true,
+        }
+    }
+
+    // A geographic zone wipes out everything it covers; the others pick victims map-wide
+    pub fn is_zone(&self) -> bool {
+        matches!(self, DisasterRegion::South { .. } | DisasterRegion::Circle { .. })
+    }
+}
+
+// Maps grid coordinates onto real lat/lon, so maps can overlay any region
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct GeoProjection {
+    pub origin_lat: f64, // Latitude of grid y = 0
+    pub origin_lon: f64, // Longitude of grid x = 0
+    pub scale: f64,      // Degrees per grid unit
+}
+
+impl Default for GeoProjection {
+    // Nice, France: 200 units = ~0.02 degrees (~2km)
+    fn default() -> Self {
+        GeoProjection { origin_lat: 43.70, origin_lon: 7.25, scale: 0.0001 }
+    }
+}
+
+impl GeoProjection {
+    pub fn to_lat_lon(&self, (x, y): (f64, f64)) -> (f64, f64) {
+        (self.origin_lat + y * self.scale, self.origin_lon + x * self.scale)
+    }
+}
+
+// Initial node positions
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Placement {
+    Uniform,                                // Uniform random over the grid (legacy)
+    Grid,                                   // Regular lattice, row by row in id order
+    Clustered { clusters: u32, spread: f64 }, // Villages: nodes within `spread` of random centres
+}
+
+// Where base stations go, independently of the phones
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum BsPlacement {
+    SameAsPhones,          // Follow `placement` (legacy)
+    Uniform,               // Uniform random over the grid, whatever the phones do
+    EdgeBiased,            // Mostly near the border: hilltops and rooftops on the outskirts
+    Sites(Vec<(f64, f64)>), // Explicit tower sites, in base-station order; any extras follow `placement`
+}
+
+// How many packets the source injects each step
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum TrafficPattern {
+    Constant(u32),                             // `rate` packets every step
+    Burst { start: i32, end: i32, rate: u32 }, // 1 packet/step, `rate` per step inside [start, end]
+    Poisson { lambda: f64 },                   // Poisson-distributed count, mean `lambda`
+    Once { count: u32 },                       // `count` packets at step 1, nothing after (closed system)
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)] // Logs from older versions lack newer fields
+pub struct SimConfig {
+    pub seed: Option<u64>, // None = fresh entropy each run
+    pub node_count: u32,
+    pub grid_size: f64,
+    pub placement: Placement,
+    pub bs_ratio: f64, // Chance each node is drawn as a base station
+    pub bs_placement: BsPlacement,
+    pub position_jitter_stddev: f64, // Gaussian nudge on every placed node; 0 = exact placement
+    pub jitter_seed: u64, // Drives the jitter alone, so one layout can be perturbed many ways
+    pub projection: GeoProjection,
+    pub max_steps: i32, // Packet generation stops after this step
+    pub ttl: u32,
+    pub run_until_empty: bool, // Keep stepping past max_steps until in-flight packets drain
+    pub target: TargetSelection,
+    pub interference: bool,
+    pub interference_factor: f64,
+    pub link_loss: f64,      // Drop probability of a copy over a range-edge link, scaled by 1 - link quality
+    pub weak_link_cost: f32, // Extra sender energy per copy over a range-edge link (link-layer retries), same scaling
+    pub battery_range: (f32, f32), // Initial smartphone battery, drawn uniformly
+    pub bs_harvest_rate: f32, // Energy a base station regains per step
+    pub bs_activation_range: Option<(i32, i32)>, // Base stations come online at a step drawn from this range
+    pub phone_harvest_rate: f32, // Same for smartphones (solar chargers); 0 = none
+    pub traffic_pattern: TrafficPattern,
+    pub packet_size_range: (u32, u32), // Bytes per message, drawn uniformly (SOS text .. photo)
+    pub cost_idle: f32,
+    pub cost_tx: f32,
+    pub cost_rx: f32,
+    pub energy_per_byte: f32, // Extra TX/RX cost per byte on top of cost_tx / cost_rx
+    pub oscillation_factor: f64,
+    pub distance_metric: DistanceMetric,
+    pub transmission_mode: TransmissionMode,
+    pub sla_deadline_steps: u32,
+    pub sla_target: f64, // Fraction of delivered messages that must meet the deadline
+    pub churn_prob: f64, // Per-step chance an active smartphone drops offline
+    pub recovery_prob: f64, // Per-step chance a churned smartphone comes back
+    pub survival_threshold: f64, // Oracle fires once survival among a disaster's victims drops to this
+    pub payout_scaling: bool, // Scale the payout by severity (1 - survival rate)
+    pub max_hint_age: Option<i32>, // Steps before a packet's target position is too stale to steer by
+    pub disaster_region: DisasterRegion,
+    pub extra_disaster_regions: Vec<DisasterRegion>, // Struck at the same step: compound disasters, insured separately
+    pub disaster_spares_base_stations: bool, // Hardened infrastructure: only phones die in the disaster
+    pub hardened_nodes: BTreeSet<u32>, // Ruggedized node ids the disaster never kills, wherever they stand
+    pub cooperation_prob: f64, // Chance a smartphone actually relays when asked (1.0 = nobody free-rides)
+    pub fair_load_decay: f64, // FairSwarm: per-step decay of each node's recent relay load (0 = only this step counts)
+    pub duty_cycle: f64, // Share of steps a smartphone's radio is on; asleep it pays no idle cost and can't relay
+    pub relay_cutoff_battery: f32, // Charge fraction below which a smartphone stops relaying for others; 0 = never
+    pub role_ratios: (f64, f64), // Share of smartphones running (relay-only, endpoint-only) firmware
+    pub relay_rewards: bool, // Mint REWARD_RELAY per relay in the tokenomics modes
+    pub halving_interval: Option<i32>, // The relay reward halves every this many steps; None = flat
+    pub progress_reward: f32, // Relay reward multiplier for forwards that bring a packet closer to the target or a base station; 1 = flat
+    pub premium_per_relay: f32,
+    pub forward_progress_only: bool, // On top of any mode: only forward to neighbors closer to the target's last known spot
+    pub flood_suppression: bool, // Flooding nodes drop messages they've already seen, run-wide
+    pub suppression_window: Option<i32>, // Flood suppression forgets a message this many steps after a node held it; None = never
+    pub base_station_backhaul: bool, // Reaching any live base station counts as delivered (it's on the internet)
+    pub hello_interval: Option<i32>, // Routing overhead: non-flooding nodes beacon to their neighbors every this many steps
+    pub carry_buffer: Option<usize>, // Store-carry-forward: packets a node may hold when nobody takes them; None = drop
+    pub carry_max_hold: i32, // Steps a holder keeps retrying a carried packet before giving up
+    pub basestation_ttl_refresh: bool, // A base station forwards with the TTL back at its initial value
+    pub backbone: bool, // Live base stations are wired together: a packet at one jumps to the one nearest the target
+    pub step_energy_budget: Option<f32>, // Max energy a node may spend per step before it holds packets back
+    pub global_tx_budget_per_step: Option<u32>, // Shared spectrum: transmissions the whole mesh gets per step
+    pub max_queue_size: Option<usize>, // Finite buffers: past this many queued copies the oldest are dropped
+    pub retry_after: Option<i32>, // Source re-sends a message still undelivered after this many steps
+    pub max_retries: u32,         // Re-sends per message when retry_after is set
+    // Importance kept per step of a message's age (decay^age): stale copies are queued
+    // last and the source gives up re-sending below importance_floor. None = no decay.
+    pub importance_decay: Option<f64>,
+    pub importance_floor: f64,
+}
+
+impl Default for SimConfig {
+    fn default() -> Self {
+        SimConfig {
+            seed: None,
+            node_count: NODE_COUNT,
+            grid_size: GRID_SIZE,
+            placement: Placement::Uniform,
+            bs_ratio: BS_RATIO,
+            bs_placement: BsPlacement::SameAsPhones,
+            position_jitter_stddev: 0.0,
+            jitter_seed: 0,
+            projection: GeoProjection::default(),
+            max_steps: MAX_STEPS,
+            ttl: PACKET_TTL,
+            run_until_empty: false,
+            target: TargetSelection::LastNode,
+            interference: false,
+            interference_factor: INTERFERENCE_FACTOR,
+            link_loss: 0.0,      // Legacy: in range = delivered
+            weak_link_cost: 0.0,
+            battery_range: (BATTERY_FULL_SMARTPHONE, BATTERY_FULL_SMARTPHONE),
+            bs_harvest_rate: HARVEST_BASE_STATION,
+            bs_activation_range: None, // Legacy: every station is up from step 1
+            phone_harvest_rate: 0.0,
+            traffic_pattern: TrafficPattern::Constant(1),
+            packet_size_range: (PACKET_SIZE_BYTES, PACKET_SIZE_BYTES),
+            cost_idle: COST_IDLE,
+            cost_tx: COST_TX,
+            cost_rx: COST_RX,
+            energy_per_byte: 0.0, // Legacy: size doesn't matter
+            oscillation_factor: OSCILLATION_FACTOR,
+            distance_metric: DistanceMetric::Euclidean,
+            transmission_mode: TransmissionMode::Broadcast,
+            sla_deadline_steps: SLA_DEADLINE_STEPS,
+            sla_target: SLA_TARGET,
+            churn_prob: 0.0,
+            recovery_prob: 0.0,
+            survival_threshold: 0.0, // Legacy: only a total wipe-out pays
+            payout_scaling: false,
+            max_hint_age: None,
+            disaster_region: DisasterRegion::South { y_max: DISASTER_ZONE_Y },
+            extra_disaster_regions: Vec::new(),
+            disaster_spares_base_stations: false,
+            hardened_nodes: BTreeSet::new(),
+            cooperation_prob: 1.0,
+            relay_cutoff_battery: 0.0,
+            fair_load_decay: FAIR_LOAD_DECAY,
+            duty_cycle: 1.0, // Legacy: always listening
+            role_ratios: (0.0, 0.0), // Every phone is an ordinary peer
+            relay_rewards: true,
+            halving_interval: None,
+            progress_reward: 1.0,
+            premium_per_relay: PREMIUM_PER_RELAY,
+            forward_progress_only: false,
+            flood_suppression: false,
+            suppression_window: None,
+            base_station_backhaul: false,
+            hello_interval: None,
+            carry_buffer: None,
+            carry_max_hold: 10,
+            basestation_ttl_refresh: false,
+            backbone: false,
+            step_energy_budget: None,
+            global_tx_budget_per_step: None, // Legacy: unlimited airtime
+            max_queue_size: None,
+            retry_after: None, // Fire and forget
+            max_retries: 3,
+            importance_decay: None,
+            importance_floor: IMPORTANCE_FLOOR,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigError {
+    TooFewNodes(u32),
+    TargetOutOfRange { target: u32, node_count: u32 },
+    HardenedOutOfRange { id: u32, node_count: u32 },
+    TargetIsSource(u32),
+    DisasterAfterEnd { disaster_step: i32, max_steps: i32 },
+    OutOfRange { field: &'static str, value: f64, min: f64, max: f64 },
+    InvalidBatteryRange(f32, f32),
+    InvalidBurst { start: i32, end: i32 },
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::TooFewNodes(n) => write!(f, "node count must be at least 2 (got {})", n),
+            ConfigError::TargetOutOfRange { target, node_count } => {
+                write!(f, "target id {} is out of range (nodes are 0..{})", target, node_count)
+            }
+            ConfigError::TargetIsSource(id) => {
+                write!(f, "target id {} is the source node, so every message would arrive at hop 0", id)
+            }
+            ConfigError::HardenedOutOfRange { id, node_count } => {
+                write!(f, "hardened node id {} is out of range (nodes are 0..{})", id, node_count)
+            }
+            ConfigError::DisasterAfterEnd { disaster_step, max_steps } => write!(
+                f,
+                "disaster at step {} would never happen with only {} steps",
+                disaster_step, max_steps
+            ),
+            ConfigError::OutOfRange { field, value, min, max } => {
+                write!(f, "{} must be within {}..={} (got {})", field, min, max, value)
+            }
+            ConfigError::InvalidBatteryRange(min, max) => {
+                write!(f, "battery range {}:{} must be positive with min <= max", min, max)
+            }
+            ConfigError::InvalidBurst { start, end } => {
+                write!(f, "burst window {}..{} ends before it starts", start, end)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+fn check_range(field: &'static str, value: f64, min: f64, max: f64) -> Result<(), ConfigError> {
+    if (min..=max).contains(&value) {
+        Ok(())
+    } else {
+        Err(ConfigError::OutOfRange { field, value, min, max })
+    }
+}
+
+impl SimConfig {
+    pub fn tx_cost(&self, size_bytes: u32) -> f32 {
+        self.cost_tx + self.energy_per_byte * size_bytes as f32
+    }
+
+    pub fn rx_cost(&self, size_bytes: u32) -> f32 {
+        self.cost_rx + self.energy_per_byte * size_bytes as f32
+    }
+
+    // Tokens minted per relay at `step`: REWARD_RELAY, halved once per completed interval
+    pub fn relay_reward(&self, step: i32) -> f32 {
+        match self.halving_interval {
+            Some(interval) => REWARD_RELAY * 0.5f32.powi((step - 1).max(0) / interval),
+            None => REWARD_RELAY,
+        }
+    }
+
+    // Every region struck at DISASTER_STEP, primary first
+    pub fn disaster_regions(&self) -> Vec<DisasterRegion> {
+        std::iter::once(self.disaster_region).chain(self.extra_disaster_regions.iter().copied()).collect()
+    }
+
+    // Catch nonsensical configs up front instead of panicking deep in the step loop
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.node_count < 2 {
+            return Err(ConfigError::TooFewNodes(self.node_count));
+        }
+        if let TargetSelection::ById(target) = self.target
+            && target >= self.node_count
+        {
+            return Err(ConfigError::TargetOutOfRange { target, node_count: self.node_count });
+        }
+        // Fixed-source flows start at node 0; random pairs always draw two distinct nodes
+        if self.target == TargetSelection::ById(0) {
+            return Err(ConfigError::TargetIsSource(0));
+        }
+        if let Some(&id) = self.hardened_nodes.iter().find(|&&id| id >= self.node_count) {
+            return Err(ConfigError::HardenedOutOfRange { id, node_count: self.node_count });
+        }
+        if self.max_steps < DISASTER_STEP {
+            return Err(ConfigError::DisasterAfterEnd { disaster_step: DISASTER_STEP, max_steps: self.max_steps });
+        }
+        check_range("grid_size", self.grid_size, f64::MIN_POSITIVE, f64::MAX)?;
+        if let Placement::Clustered { clusters, spread } = self.placement {
+            check_range("clusters", clusters as f64, 1.0, self.node_count as f64)?;
+            check_range("cluster spread", spread, 0.0, f64::MAX)?;
+        }
+        check_range("ttl", self.ttl as f64, 1.0, f64::MAX)?;
+        let (min, max) = self.battery_range;
+        if !(min > 0.0 && min <= max) {
+            return Err(ConfigError::InvalidBatteryRange(min, max));
+        }
+        let (min_size, max_size) = self.packet_size_range;
+        check_range("packet size max", max_size as f64, min_size as f64, f64::MAX)?;
+        for (field, cost) in [("cost_idle", self.cost_idle), ("cost_tx", self.cost_tx), ("cost_rx", self.cost_rx)] {
+            check_range(field, cost as f64, 0.0, f64::MAX)?;
+        }
+        check_range("energy_per_byte", self.energy_per_byte as f64, 0.0, f64::MAX)?;
+        if let Some((first, last)) = self.bs_activation_range {
+            check_range("bs activation end", last as f64, first as f64, f64::MAX)?;
+        }
+        check_range("position_jitter_stddev", self.position_jitter_stddev, 0.0, f64::MAX)?;
+        check_range("bs_ratio", self.bs_ratio, 0.0, 1.0)?;
+        check_range("bs_harvest_rate", self.bs_harvest_rate as f64, 0.0, f64::MAX)?;
+        check_range("phone_harvest_rate", self.phone_harvest_rate as f64, 0.0, f64::MAX)?;
+        match self.traffic_pattern {
+            TrafficPattern::Burst { start, end, .. } if end < start => {
+                return Err(ConfigError::InvalidBurst { start, end });
+            }
+            TrafficPattern::Poisson { lambda } => check_range("poisson lambda", lambda, 0.0, f64::MAX)?,
+            _ => {}
+        }
+        if let BsPlacement::Sites(sites) = &self.bs_placement {
+            for &(x, y) in sites {
+                check_range("base station site x", x, 0.0, self.grid_size)?;
+                check_range("base station site y", y, 0.0, self.grid_size)?;
+            }
+        }
+        check_range("projection origin_lat", self.projection.origin_lat, -90.0, 90.0)?;
+        check_range("projection origin_lon", self.projection.origin_lon, -180.0, 180.0)?;
+        check_range("projection scale", self.projection.scale, f64::MIN_POSITIVE, f64::MAX)?;
+        check_range("interference_factor", self.interference_factor, 0.0, 1.0)?;
+        check_range("link_loss", self.link_loss, 0.0, 1.0)?;
+        check_range("weak_link_cost", self.weak_link_cost as f64, 0.0, f64::MAX)?;
+        check_range("oscillation_factor", self.oscillation_factor, f64::MIN_POSITIVE, f64::MAX)?;
+        check_range("sla_target", self.sla_target, 0.0, 1.0)?;
+        check_range("churn_prob", self.churn_prob, 0.0, 1.0)?;
+        check_range("recovery_prob", self.recovery_prob, 0.0, 1.0)?;
+        check_range("survival_threshold", self.survival_threshold, 0.0, 1.0)?;
+        check_range("cooperation_prob", self.cooperation_prob, 0.0, 1.0)?;
+        check_range("fair_load_decay", self.fair_load_decay, 0.0, 1.0)?;
+        check_range("duty_cycle", self.duty_cycle, f64::MIN_POSITIVE, 1.0)?;
+        if let Some(window) = self.suppression_window {
+            check_range("suppression_window", window as f64, 1.0, f64::MAX)?;
+        }
+        if let Some(interval) = self.hello_interval {
+            check_range("hello_interval", interval as f64, 1.0, f64::MAX)?;
+        }
+        check_range("carry_max_hold", self.carry_max_hold as f64, 1.0, f64::MAX)?;
+        check_range("relay_cutoff_battery", self.relay_cutoff_battery as f64, 0.0, 1.0)?;
+        let (relay_ratio, endpoint_ratio) = self.role_ratios;
+        check_range("relay ratio", relay_ratio, 0.0, 1.0)?;
+        check_range("endpoint ratio", endpoint_ratio, 0.0, 1.0)?;
+        check_range("relay + endpoint ratio", relay_ratio + endpoint_ratio, 0.0, 1.0)?;
+        if let Some(interval) = self.halving_interval {
+            check_range("halving_interval", interval as f64, 1.0, f64::MAX)?;
+        }
+        check_range("progress_reward", self.progress_reward as f64, 1.0, f64::MAX)?;
+        check_range("premium_per_relay", self.premium_per_relay as f64, 0.0, f64::MAX)?;
+        for region in self.disaster_regions() {
+            match region {
+                DisasterRegion::Fraction { fraction } => check_range("disaster fraction", fraction, 0.0, 1.0)?,
+                DisasterRegion::TargetHubs { count } => check_range("hub count", count as f64, 0.0, self.node_count as f64)?,
+                DisasterRegion::Circle { radius, .. } => check_range("disaster radius", radius, 0.0, f64::MAX)?,
+                DisasterRegion::South { .. } => {}
+            }
+        }
+        if let Some(after) = self.retry_after {
+            check_range("retry_after", after as f64, 1.0, f64::MAX)?;
+        }
+        if let Some(decay) = self.importance_decay {
+            check_range("importance_decay", decay, 0.0, 1.0)?;
+        }
+        check_range("importance_floor", self.importance_floor, 0.0, 1.0)?;
+        if let Some(budget) = self.step_energy_budget {
+            check_range("step_energy_budget", budget as f64, f64::MIN_POSITIVE, f64::MAX)?;
+        }
+        if let Some(slots) = self.global_tx_budget_per_step {
+            check_range("global_tx_budget_per_step", slots as f64, 1.0, f64::MAX)?;
+        }
+        if let Some(size) = self.max_queue_size {
+            check_range("max_queue_size", size as f64, 1.0, f64::MAX)?;
+        }
+        if let Some(age) = self.max_hint_age {
+            check_range("max_hint_age", age as f64, 0.0, f64::MAX)?;
+        }
+        Ok(())
+    }
+}
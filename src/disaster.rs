@@ -0,0 +1,114 @@
+use std::collections::BTreeSet;
+
+use crate::config::{DisasterRegion, SimConfig, TargetSelection, DISASTER_STEP};
+use crate::node::{Node, NodeType};
+use crate::sim::SimRng;
+
+// Whatever knocks nodes out beyond batteries and churn. `apply` runs once per
+// step, before churn and the oracle: it destroys what it hits (down, battery
+// empty) and returns those ids in ascending order, offline nodes it wiped out
+// included. Those ids are what its parametric policy insures: the oracle pays
+// them once too few are back up. `rng` is the simulation's shared stream, so
+// seeded runs stay reproducible. Rolling blackouts, correlated failures or an
+// adversary picking targets as the run goes are all just implementations.
+pub trait DisasterModel {
+    fn apply(&self, nodes: &mut [Node], step: i32, rng: &mut SimRng) -> Vec<u32>;
+}
+
+// A built-in DisasterRegion striking once, at DISASTER_STEP
+#[derive(Debug, Clone, PartialEq)]
+pub struct RegionDisaster {
+    pub region: DisasterRegion,
+    pub step: i32,
+    pub hardened: BTreeSet<u32>, // Never destroyed, wherever they stand
+    pub spare_base_stations: bool,
+    pub spared_target: Option<u32>, // Random pair's target: Fraction and TargetHubs leave it be
+}
+
+impl RegionDisaster {
+    // One model per configured region, in SimConfig::disaster_regions order
+    pub fn from_config(config: &SimConfig, target: u32) -> Vec<RegionDisaster> {
+        let spared_target = (config.target == TargetSelection::RandomPair).then_some(target);
+        config.disaster_regions().into_iter()
+            .map(|region| RegionDisaster {
+                region,
+                step: DISASTER_STEP,
+                hardened: config.hardened_nodes.clone(),
+                spare_base_stations: config.disaster_spares_base_stations,
+                spared_target,
+            })
+            .collect()
+    }
+
+    fn exposed(&self, node: &Node) -> bool {
+        node.is_active
+            && !(self.spare_base_stations && node.node_type == NodeType::BaseStation)
+            && !self.hardened.contains(&node.id)
+    }
+
+    // Ids the region destroys among the nodes up right now
+    fn victims(&self, nodes: &[Node], rng: &mut SimRng) -> Vec<u32> {
+        match self.region {
+            DisasterRegion::South { .. } | DisasterRegion::Circle { .. } => nodes.iter()
+                .filter(|n| self.exposed(n) && self.region.covers(n.position))
+                .map(|n| n.id)
+                .collect(),
+            DisasterRegion::Fraction { fraction } => {
+                // Sorted candidates + seeded sampling without replacement = same victims per seed
+                let mut candidates: Vec<u32> = nodes.iter()
+                    .filter(|n| self.exposed(n) && Some(n.id) != self.spared_target)
+                    .map(|n| n.id)
+                    .collect();
+                candidates.sort_unstable();
+                let amount = (fraction * candidates.len() as f64).round() as usize;
+                let mut victims: Vec<u32> = rand::seq::index::sample(rng, candidates.len(), amount)
+                    .into_iter()
+                    .map(|i| candidates[i])
+                    .collect();
+                victims.sort_unstable();
+                victims
+            }
+            DisasterRegion::TargetHubs { count } => {
+                // Most-connected first; ties go to the lower id so the attack is deterministic
+                let mut hubs: Vec<&Node> = nodes.iter()
+                    .filter(|n| self.exposed(n) && Some(n.id) != self.spared_target)
+                    .collect();
+                hubs.sort_by_key(|n| (std::cmp::Reverse(n.peers.len()), n.id));
+                let mut hubs: Vec<u32> = hubs.iter().take(count as usize).map(|n| n.id).collect();
+                hubs.sort_unstable();
+                hubs
+            }
+        }
+    }
+}
+
+impl DisasterModel for RegionDisaster {
+    fn apply(&self, nodes: &mut [Node], step: i32, rng: &mut SimRng) -> Vec<u32> {
+        if step != self.step {
+            return Vec::new();
+        }
+        let mut destroyed = self.victims(nodes, rng);
+        for &id in &destroyed {
+            let node = &mut nodes[id as usize];
+            node.is_active = false;
+            node.battery_level = 0.0;
+        }
+        if self.region.is_zone() {
+            // Offline phones in the zone are destroyed too and won't come back
+            // (stations not yet deployed aren't there to be hit)
+            for node in nodes.iter_mut() {
+                if !node.is_active
+                    && node.battery_level > 0.0
+                    && node.activation_step <= step
+                    && self.region.covers(node.position)
+                    && !self.hardened.contains(&node.id)
+                {
+                    node.battery_level = 0.0;
+                    destroyed.push(node.id);
+                }
+            }
+            destroyed.sort_unstable();
+        }
+        destroyed
+    }
+}
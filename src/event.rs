@@ -0,0 +1,65 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DeathCause {
+    Disaster,
+    Battery,
+    Churn, // Random offline event; may come back
+}
+
+// Why a copy left the network undelivered. A lost message is charged to the
+// reason its last copy died for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DropReason {
+    Ttl,
+    DeadHolder { cause: DeathCause, step: i32 }, // The relay holding it went down, and when
+    DeadTarget, // Arrived after the target died
+    Refused, // A free-riding or low-battery relay sat on it
+    Congestion, // Pushed out of a full queue
+    Loss, // Sent, but every copy died on a weak link or to interference
+    NoRoute, // Sent, but no neighbor would take it
+}
+
+impl DropReason {
+    pub fn label(&self) -> String {
+        match self {
+            DropReason::Ttl => "TTL expired".to_string(),
+            DropReason::DeadHolder { cause: DeathCause::Disaster, step } => format!("disaster @ {}", step),
+            DropReason::DeadHolder { cause: DeathCause::Battery, .. } => "relay battery".to_string(),
+            DropReason::DeadHolder { cause: DeathCause::Churn, .. } => "relay churned".to_string(),
+            DropReason::DeadTarget => "target dead".to_string(),
+            DropReason::Refused => "refused".to_string(),
+            DropReason::Congestion => "queue full".to_string(),
+            DropReason::Loss => "link loss".to_string(),
+            DropReason::NoRoute => "no route".to_string(),
+        }
+    }
+}
+
+// Typed simulation events, fired as they happen so callers can react
+// without parsing the exported JSON afterwards.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SimEvent {
+    Disaster { step: i32, destroyed: u32 },
+    OraclePayout { step: i32, recipients: u32, amount_usdc: f32 }, // amount per recipient
+    Delivery { step: i32, packet_id: String, hops: u32 },
+    NodeDeath { step: i32, node_id: u32, cause: DeathCause },
+    NodeRecovery { step: i32, node_id: u32 },
+    BaseStationOnline { step: i32, node_id: u32 }, // A late-deployed station was switched on
+    Partition { step: i32 }, // Source can no longer reach the target
+    TargetLost { step: i32, target_id: u32 },
+    Oscillating { step: i32, packet_id: String },
+}
+
+impl SimEvent {
+    // Tag written to `SimLog.events`; per-packet/per-node noise stays out of the log
+    pub fn log_tag(&self) -> Option<String> {
+        match self {
+            SimEvent::Disaster { .. } => Some("DISASTER_START".to_string()),
+            SimEvent::OraclePayout { .. } => Some("ORACLE_PAYOUT".to_string()),
+            SimEvent::Partition { .. } => Some("PARTITION".to_string()),
+            SimEvent::TargetLost { .. } => Some("TARGET_LOST".to_string()),
+            SimEvent::BaseStationOnline { node_id, .. } => Some(format!("BS_ONLINE:{}", node_id)),
+            SimEvent::Oscillating { packet_id, .. } => Some(format!("OSCILLATING:{}", packet_id)),
+            SimEvent::Delivery { .. } | SimEvent::NodeDeath { .. } | SimEvent::NodeRecovery { .. } => None,
+        }
+    }
+}
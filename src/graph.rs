@@ -0,0 +1,399 @@
+use std::collections::btree_map::Entry;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+use crate::config::DistanceMetric;
+use crate::node::{Node, NodeType};
+
+// Bucket grid over node positions with cells a hair wider than the longest range, so
+// everyone in range of a node (under either metric) is in its cell or one of the 8 around it
+#[derive(Debug, Clone)]
+pub struct SpatialGrid {
+    cell: f64,
+    cells: BTreeMap<(i64, i64), BTreeSet<u32>>,
+}
+
+impl SpatialGrid {
+    pub fn new(nodes: &[Node]) -> Self {
+        let longest = nodes.iter().map(|n| n.transmission_range).fold(0.0, f64::max);
+        // The margin keeps float rounding from putting an in-range pair two cells apart
+        let cell = if longest > 0.0 { longest * (1.0 + 1e-9) } else { 1.0 };
+        let mut grid = SpatialGrid { cell, cells: BTreeMap::new() };
+        for node in nodes {
+            grid.insert(node.id, node.position);
+        }
+        grid
+    }
+
+    fn key(&self, (x, y): (f64, f64)) -> (i64, i64) {
+        ((x / self.cell).floor() as i64, (y / self.cell).floor() as i64)
+    }
+
+    pub fn insert(&mut self, id: u32, position: (f64, f64)) {
+        let key = self.key(position);
+        self.cells.entry(key).or_default().insert(id);
+    }
+
+    pub fn remove(&mut self, id: u32, position: (f64, f64)) {
+        let key = self.key(position);
+        if let Entry::Occupied(mut cell) = self.cells.entry(key) {
+            cell.get_mut().remove(&id);
+            if cell.get().is_empty() {
+                cell.remove();
+            }
+        }
+    }
+
+    // Only the two cells involved change; a move within one cell is free
+    pub fn move_node(&mut self, id: u32, old: (f64, f64), new: (f64, f64)) {
+        if self.key(old) != self.key(new) {
+            self.remove(id, old);
+            self.insert(id, new);
+        }
+    }
+
+    // Everyone who could be in range of `position`: its cell and the 8 around it, in id order
+    pub fn nearby(&self, position: (f64, f64)) -> BTreeSet<u32> {
+        let (cx, cy) = self.key(position);
+        (cx - 1..=cx + 1)
+            .flat_map(|x| (cy - 1..=cy + 1).map(move |y| (x, y)))
+            .filter_map(|key| self.cells.get(&key))
+            .flatten()
+            .copied()
+            .collect()
+    }
+}
+
+// Static radio topology: who is within range of whom, regardless of who is still alive.
+// Ordered maps throughout so every query walks nodes in id order, run after run.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MeshGraph {
+    adjacency: BTreeMap<u32, Vec<u32>>,
+    quality: BTreeMap<(u32, u32), f64>, // Per directed link: 1 - distance / range, so 1 = co-located, 0 = range edge
+}
+
+impl MeshGraph {
+    // Directed edge i -> j whenever j is inside i's transmission range. Range checks
+    // only run against the grid neighborhood, not every pair.
+    pub fn build(nodes: &[Node], metric: DistanceMetric) -> Self {
+        let grid = SpatialGrid::new(nodes);
+        let by_id: BTreeMap<u32, &Node> = nodes.iter().map(|n| (n.id, n)).collect();
+        let mut graph = MeshGraph::default();
+        for node in nodes {
+            let mut peers = Vec::new();
+            for other in grid.nearby(node.position).into_iter().filter(|&id| id != node.id).map(|id| by_id[&id]) {
+                if let Some(quality) = Self::link(node, other, metric) {
+                    peers.push(other.id);
+                    graph.quality.insert((node.id, other.id), quality);
+                }
+            }
+            graph.adjacency.insert(node.id, peers);
+        }
+        graph
+    }
+
+    fn link(from: &Node, to: &Node, metric: DistanceMetric) -> Option<f64> {
+        let distance = from.distance_to(to, metric);
+        (distance <= from.transmission_range).then(|| 1.0 - distance / from.transmission_range)
+    }
+
+    // Incremental update after `id` moved away from `old` (its new position already in
+    // `nodes`, which is indexed by id, and in `grid`). Only the node's own links and
+    // those of nodes near either position are rechecked; the result matches a rebuild.
+    pub fn relink(&mut self, nodes: &[Node], metric: DistanceMetric, grid: &SpatialGrid, id: u32, old: (f64, f64)) {
+        let node = &nodes[id as usize];
+        let mut affected = grid.nearby(old);
+        affected.extend(grid.nearby(node.position));
+        affected.remove(&id);
+
+        // Its own links: drop the old ones, find the new ones around where it is now
+        for other in self.adjacency.remove(&id).unwrap_or_default() {
+            self.quality.remove(&(id, other));
+        }
+        let mut peers = Vec::new();
+        for &other in grid.nearby(node.position).iter().filter(|&&other| other != id) {
+            if let Some(quality) = Self::link(node, &nodes[other as usize], metric) {
+                peers.push(other);
+                self.quality.insert((id, other), quality);
+            }
+        }
+        self.adjacency.insert(id, peers);
+
+        // Links into it: only nodes near the old or new spot could have had or gained one
+        for &other in &affected {
+            let list = self.adjacency.entry(other).or_default();
+            let position = list.binary_search(&id);
+            match (Self::link(&nodes[other as usize], node, metric), position) {
+                (Some(quality), Err(at)) => {
+                    list.insert(at, id);
+                    self.quality.insert((other, id), quality);
+                }
+                (Some(quality), Ok(_)) => {
+                    self.quality.insert((other, id), quality);
+                }
+                (None, Ok(at)) => {
+                    list.remove(at);
+                    self.quality.remove(&(other, id));
+                }
+                (None, Err(_)) => {}
+            }
+        }
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.adjacency.len()
+    }
+
+    pub fn neighbors(&self, id: u32) -> &[u32] {
+        self.adjacency.get(&id).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    pub fn degree(&self, id: u32) -> usize {
+        self.neighbors(id).len()
+    }
+
+    // Links are one-way: a base station's 180 reaches phones whose 40 can't answer.
+    // Anything travelling back (e.g. an ACK) must check the reverse link itself.
+    pub fn has_link(&self, from: u32, to: u32) -> bool {
+        self.neighbors(from).contains(&to)
+    }
+
+    // None when there's no from -> to link
+    pub fn link_quality(&self, from: u32, to: u32) -> Option<f64> {
+        self.quality.get(&(from, to)).copied()
+    }
+
+    // Nodes that can hear `id` (links pointing into it)
+    pub fn in_neighbors(&self, id: u32) -> Vec<u32> {
+        self.adjacency.iter()
+            .filter(|(_, peers)| peers.contains(&id))
+            .map(|(&from, _)| from)
+            .collect()
+    }
+
+    // The part of the topology still standing: live nodes and the links between them
+    pub fn survivors(&self, nodes: &[Node]) -> MeshGraph {
+        self.survivors_where(|id| nodes[id as usize].is_active)
+    }
+
+    // Same, for any notion of alive (e.g. a snapshot taken before a disaster)
+    pub fn survivors_where(&self, alive: impl Fn(u32) -> bool) -> MeshGraph {
+        let adjacency = self.adjacency.iter()
+            .filter(|&(&id, _)| alive(id))
+            .map(|(&id, peers)| (id, peers.iter().copied().filter(|&peer| alive(peer)).collect()))
+            .collect();
+        let quality = self.quality.iter()
+            .filter(|&(&(from, to), _)| alive(from) && alive(to))
+            .map(|(&link, &q)| (link, q))
+            .collect();
+        MeshGraph { adjacency, quality }
+    }
+
+    // Mean out-degree; 0 for an empty graph
+    pub fn avg_degree(&self) -> f64 {
+        if self.adjacency.is_empty() { 0.0 } else { self.link_count() as f64 / self.node_count() as f64 }
+    }
+
+    pub fn largest_component(&self) -> usize {
+        self.connected_components().iter().map(Vec::len).max().unwrap_or(0)
+    }
+
+    pub fn link_count(&self) -> usize {
+        self.adjacency.values().map(Vec::len).sum()
+    }
+
+    // Links whose reverse direction doesn't exist
+    pub fn one_way_link_count(&self) -> usize {
+        self.adjacency.iter()
+            .map(|(&from, peers)| peers.iter().filter(|&&to| !self.has_link(to, from)).count())
+            .sum()
+    }
+
+    // Weakly connected components (links treated as undirected), each sorted, ordered by smallest id
+    pub fn connected_components(&self) -> Vec<Vec<u32>> {
+        let mut undirected: BTreeMap<u32, Vec<u32>> = BTreeMap::new();
+        for (&id, peers) in &self.adjacency {
+            for &peer in peers {
+                undirected.entry(id).or_default().push(peer);
+                undirected.entry(peer).or_default().push(id);
+            }
+        }
+
+        let mut component_of: BTreeMap<u32, usize> = BTreeMap::new();
+        let mut components: Vec<Vec<u32>> = Vec::new();
+        for &start in self.adjacency.keys() {
+            if component_of.contains_key(&start) { continue; }
+            let index = components.len();
+            let mut members = vec![start];
+            component_of.insert(start, index);
+            let mut queue = VecDeque::from([start]);
+            while let Some(id) = queue.pop_front() {
+                for &peer in undirected.get(&id).map(Vec::as_slice).unwrap_or(&[]) {
+                    if let Entry::Vacant(entry) = component_of.entry(peer) {
+                        entry.insert(index);
+                        members.push(peer);
+                        queue.push_back(peer);
+                    }
+                }
+            }
+            members.sort_unstable();
+            components.push(members);
+        }
+        components
+    }
+
+    // Share of the graph's smartphones with a route (any hop count, following link
+    // direction) to some base station in the graph; None when there are no phones.
+    // On a survivors() graph that's the live phones that can still get a message out.
+    pub fn base_station_coverage(&self, nodes: &[Node]) -> Option<f64> {
+        let mut reverse: BTreeMap<u32, Vec<u32>> = BTreeMap::new();
+        for (&id, peers) in &self.adjacency {
+            for &peer in peers {
+                reverse.entry(peer).or_default().push(id);
+            }
+        }
+        // Walk backwards from every base station at once
+        let is_station = |id: &u32| nodes[*id as usize].node_type == NodeType::BaseStation;
+        let mut covered: BTreeSet<u32> = self.adjacency.keys().copied().filter(is_station).collect();
+        let mut queue: VecDeque<u32> = covered.iter().copied().collect();
+        while let Some(id) = queue.pop_front() {
+            for &from in reverse.get(&id).map(Vec::as_slice).unwrap_or(&[]) {
+                if covered.insert(from) {
+                    queue.push_back(from);
+                }
+            }
+        }
+        let phones = self.adjacency.keys().filter(|id| !is_station(id)).count();
+        (phones > 0).then(|| covered.iter().filter(|id| !is_station(id)).count() as f64 / phones as f64)
+    }
+
+    // Hops from `src` to everything it can reach, following link direction
+    pub fn hop_distances(&self, src: u32) -> BTreeMap<u32, u32> {
+        let mut distances = BTreeMap::from([(src, 0)]);
+        let mut queue = VecDeque::from([src]);
+        while let Some(id) = queue.pop_front() {
+            let next = distances[&id] + 1;
+            for &peer in self.neighbors(id) {
+                if let Entry::Vacant(entry) = distances.entry(peer) {
+                    entry.insert(next);
+                    queue.push_back(peer);
+                }
+            }
+        }
+        distances
+    }
+
+    // Longest shortest path between any two nodes that can reach each other; None without links.
+    // One BFS per node, so O(n * (n + links)): fine for analysis, not for every step.
+    pub fn diameter(&self) -> Option<u32> {
+        self.adjacency.keys()
+            .filter_map(|&id| self.hop_distances(id).into_values().max())
+            .max()
+            .filter(|&hops| hops > 0)
+    }
+
+    // Fewest-hop route following link direction, including both endpoints
+    pub fn shortest_path(&self, src: u32, dst: u32) -> Option<Vec<u32>> {
+        if !self.adjacency.contains_key(&src) || !self.adjacency.contains_key(&dst) {
+            return None;
+        }
+        let mut parent: BTreeMap<u32, u32> = BTreeMap::new();
+        let mut queue = VecDeque::from([src]);
+        parent.insert(src, src);
+        while let Some(id) = queue.pop_front() {
+            if id == dst {
+                let mut path = vec![dst];
+                let mut current = dst;
+                while current != src {
+                    current = parent[&current];
+                    path.push(current);
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for &peer in self.neighbors(id) {
+                if let Entry::Vacant(entry) = parent.entry(peer) {
+                    entry.insert(id);
+                    queue.push_back(peer);
+                }
+            }
+        }
+        None
+    }
+
+    // Relays every src -> dst route runs through: lose any one and dst is cut off.
+    // None when there's no route to begin with. Each of them lies on every route, so
+    // only the shortest path's relays need checking, one BFS apiece.
+    pub fn critical_relays(&self, src: u32, dst: u32) -> Option<BTreeSet<u32>> {
+        let path = self.shortest_path(src, dst)?;
+        Some(path[1..path.len() - 1].iter().copied()
+            .filter(|&relay| self.survivors_where(|id| id != relay).shortest_path(src, dst).is_none())
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::SimConfig;
+    use crate::node::{placed_nodes, Layout};
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
+    // Node 0 at the origin with range 40, node 1 at `offset`
+    fn pair(offset: (f64, f64)) -> Vec<Node> {
+        placed_nodes(&SimConfig::default(), &[(0.0, 0.0), offset], None)
+    }
+
+    fn linked(offset: (f64, f64), metric: DistanceMetric) -> bool {
+        MeshGraph::build(&pair(offset), metric).has_link(0, 1)
+    }
+
+    #[test]
+    fn exactly_at_range_is_a_peer() {
+        // 24-32-40 is exact in f64, so this is the boundary itself, not a rounding of it
+        let nodes = pair((24.0, 32.0));
+        assert_eq!(nodes[0].distance_to(&nodes[1], DistanceMetric::Euclidean), 40.0);
+        assert!(linked((40.0, 0.0), DistanceMetric::Euclidean));
+        assert!(linked((24.0, 32.0), DistanceMetric::Euclidean));
+        assert!(linked((20.0, 20.0), DistanceMetric::Manhattan));
+        let graph = MeshGraph::build(&pair((40.0, 0.0)), DistanceMetric::Euclidean);
+        assert_eq!(graph.link_quality(0, 1), Some(0.0));
+    }
+
+    #[test]
+    fn incremental_relinking_matches_a_rebuild() {
+        // Phones and base stations scattered over the grid, then moved around at random
+        let config = SimConfig::default();
+        let mut rng = StdRng::seed_from_u64(11);
+        let layout = Layout::new(&config, &mut rng);
+        let mut nodes: Vec<Node> = (0..40).map(|id| Node::new(id, &config, &layout, &mut rng)).collect();
+        for metric in [DistanceMetric::Euclidean, DistanceMetric::Manhattan] {
+            let mut grid = SpatialGrid::new(&nodes);
+            let mut graph = MeshGraph::build(&nodes, metric);
+            for round in 0..300 {
+                let id = rng.random_range(0..nodes.len() as u32);
+                let old = nodes[id as usize].position;
+                // Mostly short hops, sometimes across the map
+                let new = if round % 5 == 0 {
+                    (rng.random_range(0.0..config.grid_size), rng.random_range(0.0..config.grid_size))
+                } else {
+                    (old.0 + rng.random_range(-30.0..30.0), old.1 + rng.random_range(-30.0..30.0))
+                };
+                nodes[id as usize].position = new;
+                grid.move_node(id, old, new);
+                graph.relink(&nodes, metric, &grid, id, old);
+                assert_eq!(graph, MeshGraph::build(&nodes, metric), "{:?} round {}: moved {}", metric, round, id);
+            }
+        }
+    }
+
+    #[test]
+    fn one_ulp_either_side_of_range() {
+        let inside = 40.0f64.next_down();
+        let outside = 40.0f64.next_up();
+        assert!(linked((inside, 0.0), DistanceMetric::Euclidean));
+        assert!(!linked((outside, 0.0), DistanceMetric::Euclidean));
+        assert!(linked((0.0, inside), DistanceMetric::Manhattan));
+        assert!(!linked((0.0, outside), DistanceMetric::Manhattan));
+    }
+}
@@ -0,0 +1,33 @@
+// ResilientMesh: disaster-resilient mesh network simulator (Flooding vs Swarm)
+
+// println! for the run narration; goes to stderr once sim::narrate_to_stderr(true) is set
+#[macro_export]
+macro_rules! narrate {
+    ($($arg:tt)*) => {
+        if $crate::sim::narrating_to_stderr() { eprintln!($($arg)*) } else { println!($($arg)*) }
+    };
+}
+
+pub mod config;
+pub mod disaster;
+pub mod event;
+pub mod graph;
+pub mod log;
+pub mod node;
+pub mod routing;
+pub mod scenario;
+pub mod sim;
+
+pub use config::{BsPlacement, ConfigError, DisasterRegion, DistanceMetric, GeoProjection, Placement, SimConfig, SimMode, TargetSelection, TrafficPattern, TransmissionMode};
+pub use log::{BenchmarkResult, ExportError, LogExport, LogFormat, MessageTimeline, ModeResult, StepMetrics};
+pub use disaster::{DisasterModel, RegionDisaster};
+pub use event::{DeathCause, DropReason, SimEvent};
+pub use graph::{MeshGraph, SpatialGrid};
+pub use node::{Layout, Node, NodeRole, NodeType, Packet, PacketId};
+#[cfg(feature = "tokenomics")]
+pub use node::Wallet;
+#[cfg(feature = "tokenomics")]
+pub use log::{write_wallets, WalletLog};
+pub use routing::{RoutingContext, RoutingStrategy};
+pub use scenario::Scenario;
+pub use sim::{confidence_interval_95, jain_fairness, random_pair, narrate_to_stderr, DisasterImpact, run_simulation, run_simulation_timed, RunTimings, SimRng, SimState, SimStats, Simulation, StepReport, RNG_BACKEND};
@@ -0,0 +1,354 @@
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+use crate::config::{SimConfig, SimMode};
+use crate::node::Node;
+use crate::sim::{SimStats, StepReport, RNG_BACKEND};
+#[cfg(feature = "tokenomics")]
+use crate::node::Wallet;
+
+// On-disk encoding of the exported log. The visualizer reads JSON; MessagePack
+// is for big parameter sweeps where the pretty JSON runs into gigabytes.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum LogFormat {
+    #[default]
+    Json,        // simulation_log.json
+    MessagePack, // simulation_log.msgpack
+    Csv,         // simulation_log.csv: node states only, one row per node per step
+    Jsonl,       // simulation_log.jsonl: a header line, then one step per line, streamed during the run
+}
+
+impl LogFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            LogFormat::Json => "json",
+            LogFormat::MessagePack => "msgpack",
+            LogFormat::Csv => "csv",
+            LogFormat::Jsonl => "jsonl",
+        }
+    }
+
+    pub fn from_path(path: &str) -> Option<Self> {
+        match path.rsplit('.').next()? {
+            "json" => Some(LogFormat::Json),
+            "msgpack" => Some(LogFormat::MessagePack),
+            "csv" => Some(LogFormat::Csv),
+            "jsonl" => Some(LogFormat::Jsonl),
+            _ => None,
+        }
+    }
+
+    // Where run_simulation writes this format
+    pub fn default_path(&self) -> String {
+        format!("simulation_log.{}", self.extension())
+    }
+
+    pub fn encode(&self, log_file: &SimLogFile) -> Result<Vec<u8>, ExportError> {
+        Ok(match self {
+            LogFormat::Json => serde_json::to_string_pretty(log_file)?.into_bytes(),
+            // Named fields so msgpack readers get the same keys as the JSON
+            LogFormat::MessagePack => rmp_serde::to_vec_named(log_file)?,
+            LogFormat::Csv => {
+                let mut csv = String::from("step,node_id,lat,lon,is_active,node_type,battery\n");
+                for step in &log_file.steps {
+                    for n in &step.nodes {
+                        csv.push_str(&format!("{},{},{},{},{},{},{}\n",
+                            step.step, n.id, n.lat, n.lon, n.is_active, n.node_type, n.battery));
+                    }
+                }
+                csv.into_bytes()
+            }
+            LogFormat::Jsonl => {
+                let header = LogHeader { schema_version: log_file.schema_version, config: log_file.config.clone() };
+                let mut jsonl = serde_json::to_vec(&header)?;
+                jsonl.push(b'\n');
+                for step in &log_file.steps {
+                    serde_json::to_writer(&mut jsonl, step)?;
+                    jsonl.push(b'\n');
+                }
+                jsonl
+            }
+        })
+    }
+
+    pub fn write(&self, log_file: &SimLogFile, path: &str) -> Result<(), ExportError> {
+        std::fs::write(path, self.encode(log_file)?)
+            .map_err(|source| ExportError::Io { path: path.to_string(), source })
+    }
+
+    // Read back a log written by `encode` (CSV drops too much to be read back)
+    pub fn decode(&self, bytes: &[u8]) -> Result<SimLogFile, String> {
+        match self {
+            LogFormat::Json => serde_json::from_slice(bytes).map_err(|e| e.to_string()),
+            LogFormat::MessagePack => rmp_serde::from_slice(bytes).map_err(|e| e.to_string()),
+            LogFormat::Csv => Err("CSV logs only hold node states and can't be replayed".to_string()),
+            LogFormat::Jsonl => {
+                let text = std::str::from_utf8(bytes).map_err(|e| e.to_string())?;
+                let mut lines = text.lines().filter(|line| !line.trim().is_empty());
+                let header: LogHeader = serde_json::from_str(lines.next().ok_or("empty JSONL log")?)
+                    .map_err(|e| e.to_string())?;
+                let steps = lines.map(serde_json::from_str).collect::<Result<_, _>>().map_err(|e| e.to_string())?;
+                Ok(SimLogFile { schema_version: header.schema_version, config: header.config, steps })
+            }
+        }
+    }
+}
+
+// Where and how a run's log is written
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogExport {
+    pub format: LogFormat,
+    pub path: String,
+}
+
+impl LogExport {
+    // simulation_log.<ext> in the working directory
+    pub fn new(format: LogFormat) -> Self {
+        LogExport { format, path: format.default_path() }
+    }
+}
+
+// Why a log couldn't be exported
+#[derive(Debug)]
+pub enum ExportError {
+    Io { path: String, source: std::io::Error }, // e.g. read-only working directory, disk full
+    Json(serde_json::Error),
+    MessagePack(rmp_serde::encode::Error),
+}
+
+impl std::fmt::Display for ExportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExportError::Io { path, source } => write!(f, "can't write '{}': {}", path, source),
+            ExportError::Json(err) => write!(f, "can't encode the log as JSON: {}", err),
+            ExportError::MessagePack(err) => write!(f, "can't encode the log as MessagePack: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for ExportError {}
+
+impl From<serde_json::Error> for ExportError {
+    fn from(err: serde_json::Error) -> Self {
+        ExportError::Json(err)
+    }
+}
+
+impl From<rmp_serde::encode::Error> for ExportError {
+    fn from(err: rmp_serde::encode::Error) -> Self {
+        ExportError::MessagePack(err)
+    }
+}
+
+// Bump whenever the exported structure changes
+pub const SCHEMA_VERSION: u32 = 7; // v2: per-step `bounds`; v3: `battery_cov`; v4: `in_flight`; v5: node `cluster_id`; v6: `active_node_count`; v7: `timelines`
+
+// Top-level export: the effective config tells readers node count etc. without guessing
+#[derive(Serialize, Deserialize)]
+pub struct SimLogFile {
+    pub schema_version: u32,
+    pub config: SimConfig,
+    pub steps: Vec<SimLog>,
+}
+
+// First line of a JSONL log: everything in SimLogFile but the steps
+#[derive(Serialize, Deserialize)]
+struct LogHeader {
+    schema_version: u32,
+    config: SimConfig,
+}
+
+// Writes a JSONL log as the run goes, flushing every line so readers can tail it
+pub struct JsonlWriter {
+    writer: BufWriter<File>,
+    path: String,
+}
+
+impl JsonlWriter {
+    pub fn create(path: &str, config: &SimConfig) -> Result<Self, ExportError> {
+        let file = File::create(path).map_err(|source| ExportError::Io { path: path.to_string(), source })?;
+        let mut stream = JsonlWriter { writer: BufWriter::new(file), path: path.to_string() };
+        stream.write_line(&LogHeader { schema_version: SCHEMA_VERSION, config: config.clone() })?;
+        Ok(stream)
+    }
+
+    pub fn write_step(&mut self, step: &SimLog) -> Result<(), ExportError> {
+        self.write_line(step)
+    }
+
+    fn write_line(&mut self, value: &impl Serialize) -> Result<(), ExportError> {
+        serde_json::to_writer(&mut self.writer, value)?;
+        self.writer.write_all(b"\n")
+            .and_then(|_| self.writer.flush())
+            .map_err(|source| ExportError::Io { path: self.path.clone(), source })
+    }
+}
+
+// Log structure for Visualization
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct SimLog {
+    pub step: i32,
+    pub nodes: Vec<NodeLog>,
+    pub packets: Vec<PacketLog>,
+    #[serde(default)] // Absent before schema v4
+    pub in_flight: Vec<PacketLog>, // Queued copies and the path they've taken so far
+    pub events: Vec<String>,
+    #[serde(default)] // Absent before schema v2
+    pub bounds: Option<Bounds>, // None once every node is down
+    #[serde(default)] // Absent before schema v3
+    pub battery_cov: Option<f64>, // Spread of smartphone charge, see sim::battery_cov
+    #[serde(default)] // Absent before schema v6
+    pub active_node_count: usize, // Nodes up at the end of the step
+    #[serde(default)] // Absent before schema v7
+    pub timelines: Vec<MessageTimeline>, // Messages first delivered this step, hop by hop
+}
+
+// Lat/lon extent of the live nodes, so maps can follow the surviving mesh
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Bounds {
+    pub min_lat: f64,
+    pub max_lat: f64,
+    pub min_lon: f64,
+    pub max_lon: f64,
+}
+
+impl Bounds {
+    pub fn of_active(nodes: &[Node]) -> Option<Self> {
+        nodes.iter().filter(|n| n.is_active).fold(None, |bounds: Option<Bounds>, n| {
+            Some(match bounds {
+                None => Bounds { min_lat: n.lat, max_lat: n.lat, min_lon: n.lon, max_lon: n.lon },
+                Some(b) => Bounds {
+                    min_lat: b.min_lat.min(n.lat),
+                    max_lat: b.max_lat.max(n.lat),
+                    min_lon: b.min_lon.min(n.lon),
+                    max_lon: b.max_lon.max(n.lon),
+                },
+            })
+        })
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct NodeLog {
+    pub id: u32,
+    pub lat: f64,
+    pub lon: f64,
+    pub is_active: bool,
+    pub node_type: String, // "Smartphone" or "BaseStation"
+    pub battery: f32,
+    #[serde(default)] // Absent before schema v5
+    pub cluster_id: Option<u32>, // Nearest live base station (see sim::nearest_base_stations)
+}
+
+// One line of --metrics-stream: a step's headline numbers, cheap enough to chart live
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StepMetrics {
+    pub mode: SimMode,
+    pub step: i32,
+    pub active_nodes: usize,
+    pub in_flight: usize,
+    pub delivered: usize, // Messages that reached the target this step
+    pub total_energy: f32, // Cumulative
+}
+
+impl StepMetrics {
+    pub fn new(mode: SimMode, report: &StepReport) -> Self {
+        StepMetrics {
+            mode,
+            step: report.step,
+            active_nodes: report.active_nodes,
+            in_flight: report.in_flight,
+            delivered: report.delivered.len(),
+            total_energy: report.total_energy,
+        }
+    }
+}
+
+// Self-describing benchmark summary (--results): what produced the numbers travels with them
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BenchmarkResult {
+    pub crate_version: String,
+    #[serde(default)]
+    pub rng_backend: String, // Which generator the seed drove (see RNG_BACKEND)
+    pub timestamp_unix: u64, // Seconds since the epoch when the file was written
+    pub seed: Option<u64>,
+    pub config: SimConfig,
+    pub modes: Vec<ModeResult>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ModeResult {
+    pub mode: SimMode,
+    pub generated_messages: u32,
+    pub delivered_messages: usize,
+    pub delivery_ratio: f64,
+    pub total_energy: f32,
+    pub avg_latency: Option<f64>,
+    pub deliveries_per_kj: Option<f64>,
+    #[serde(default)]
+    pub delivered_bytes: u64,
+    #[serde(default)]
+    pub bytes_per_joule: Option<f64>,
+    pub steps_run: i32,
+}
+
+impl BenchmarkResult {
+    pub fn new(config: &SimConfig, runs: &[(SimMode, SimStats)]) -> Self {
+        let timestamp_unix = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs());
+        BenchmarkResult {
+            crate_version: env!("CARGO_PKG_VERSION").to_string(),
+            rng_backend: RNG_BACKEND.to_string(),
+            timestamp_unix,
+            seed: config.seed,
+            config: config.clone(),
+            modes: runs.iter().map(|(mode, stats)| ModeResult {
+                mode: *mode,
+                generated_messages: stats.generated_packets,
+                delivered_messages: stats.latencies.len(),
+                delivery_ratio: stats.delivery_ratio(),
+                total_energy: stats.total_energy,
+                avg_latency: stats.avg_latency(),
+                deliveries_per_kj: stats.deliveries_per_kj(),
+                delivered_bytes: stats.delivered_bytes,
+                bytes_per_joule: stats.bytes_per_joule(),
+                steps_run: stats.steps_run,
+            }).collect(),
+        }
+    }
+
+    pub fn write(&self, path: &str) -> Result<(), ExportError> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)
+            .map_err(|source| ExportError::Io { path: path.to_string(), source })
+    }
+}
+
+// A node's end-of-run balances, for economic analysis without the step log
+#[cfg(feature = "tokenomics")]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WalletLog {
+    pub node_id: u32,
+    #[serde(flatten)]
+    pub wallet: Wallet,
+}
+
+#[cfg(feature = "tokenomics")]
+pub fn write_wallets(wallets: &[WalletLog], path: &str) -> Result<(), ExportError> {
+    std::fs::write(path, serde_json::to_string_pretty(wallets)?)
+        .map_err(|source| ExportError::Io { path: path.to_string(), source })
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PacketLog {
+    pub id: String,
+    pub path: Vec<u32>, // Node IDs in order
+}
+
+// When a delivered message was where: (step, node) per hop, source first, for animating its journey
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MessageTimeline {
+    pub id: String,
+    pub hops: Vec<(i32, u32)>,
+}
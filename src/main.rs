@@ -1,389 +1,1435 @@
-use serde::{Serialize, Deserialize};
-use rand::Rng;
-use std::collections::{HashSet, VecDeque, HashMap};
-use std::fs::File;
-use std::io::Write;
+use rayon::prelude::*;
+use resilient_mesh_sim::narrate;
+#[cfg(feature = "tokenomics")]
+use resilient_mesh_sim::write_wallets;
+use resilient_mesh_sim::{confidence_interval_95, jain_fairness, narrate_to_stderr, BenchmarkResult, run_simulation, BsPlacement, run_simulation_timed, RunTimings, DisasterImpact, DisasterRegion, DistanceMetric, ExportError, GeoProjection, LogExport, LogFormat, Node, NodeType, Placement, Scenario, SimConfig, SimMode, SimStats, Simulation, StepMetrics, StepReport, TargetSelection, TrafficPattern, TransmissionMode};
 
-// --- 0. Constants ---
-const BATTERY_FULL_SMARTPHONE: f32 = 1000.0;
-const BATTERY_INFINITE: f32 = 999999.0;
+// constant:<rate> | burst:<start>:<end>:<rate> | poisson:<lambda>
+fn parse_traffic(value: &str) -> Option<TrafficPattern> {
+    let parts: Vec<&str> = value.split(':').collect();
+    match parts.as_slice() {
+        ["constant", rate] => Some(TrafficPattern::Constant(rate.parse().ok()?)),
+        ["burst", start, end, rate] => Some(TrafficPattern::Burst {
+            start: start.parse().ok()?,
+            end: end.parse().ok()?,
+            rate: rate.parse().ok()?,
+        }),
+        ["once", count] => Some(TrafficPattern::Once { count: count.parse().ok()? }),
+        ["poisson", lambda] => {
+            let lambda: f64 = lambda.parse().ok()?;
+            (lambda >= 0.0).then_some(TrafficPattern::Poisson { lambda })
+        }
+        _ => None,
+    }
+}
 
-const COST_IDLE: f32 = 0.5;
-const COST_TX: f32 = 5.0;
-const COST_RX: f32 = 2.0;
+// flooding | swarm | adaptive | fair-swarm | weighted:<k>
+fn parse_mode(value: &str) -> Option<SimMode> {
+    match value {
+        "flooding" => Some(SimMode::Flooding),
+        "swarm" => Some(SimMode::Swarm),
+        "adaptive" => Some(SimMode::Adaptive),
+        "fair-swarm" => Some(SimMode::FairSwarm),
+        other => other.strip_prefix("weighted:")?.parse().ok().map(|k| SimMode::WeightedSplit { k }),
+    }
+}
 
-const REWARD_RELAY: f32 = 1.0; // Token reward per relay
-const INSURANCE_PAYOUT: f32 = 10000.0; // USDC payout
+fn parse_probability(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(p) if (0.0..=1.0).contains(&p) => Ok(p),
+        _ => Err(format!("invalid probability '{}' (expected 0.0-1.0)", value)),
+    }
+}
 
-const DISASTER_STEP: i32 = 20;
+const DISASTER_SPECS: &str = "south:<y>|circle:<x>:<y>:<r>|fraction:<f>|hubs:<count>";
+const BS_SWEEP_SEEDS: u64 = 5; // Seeds averaged per ratio by --sweep-basestations without --repeat
+const SCALE_TEST_COUNTS: [u32; 4] = [100, 500, 1000, 5000]; // Node counts --scale-test runs without a list
 
-#[derive(Debug, Clone, Copy, PartialEq)]
-enum SimMode {
-    Flooding, // Old tech (Benchmark baseline)
-    Swarm,    // New tech (Unicorn)
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-enum NodeType {
-    Smartphone,
-    BaseStation,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct Wallet {
-    address: String,
-    balance_token: f32,
-    balance_usdc: f32,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct Node {
-    id: u32,
-    // (x, y) relative coordinates (0-200)
-    position: (f64, f64),
-    // Lat/Lon for visualization (calculated from position)
-    lat: f64,
-    lon: f64,
-    is_active: bool,
-    peers: Vec<u32>,
-    node_type: NodeType,
-    battery_level: f32,
-    transmission_range: f64,
-    wallet: Wallet,
-}
-
-#[derive(Debug, Clone)]
-struct Packet {
-    id: String,
-    history: Vec<u32>,
-    target_id: u32,
-    hops: u32,
-    ttl: u32,
-}
-
-// Log structure for Visualization
-#[derive(Serialize)]
-struct SimLog {
-    step: i32,
-    nodes: Vec<NodeLog>,
-    packets: Vec<PacketLog>,
-    events: Vec<String>,
-}
-
-#[derive(Serialize)]
-struct NodeLog {
-    id: u32,
-    lat: f64,
-    lon: f64,
-    is_active: bool,
-    node_type: String, // "Smartphone" or "BaseStation"
-    battery: f32,
-}
-
-#[derive(Serialize)]
-struct PacketLog {
-    id: String,
-    path: Vec<u32>, // Node IDs in order
-}
-
-impl Node {
-    fn new(id: u32) -> Self {
-        let mut rng = rand::rng();
-        // 15% BaseStation
-        let (node_type, battery, range) = if rng.random_bool(0.15) {
-            (NodeType::BaseStation, BATTERY_INFINITE, 180.0) 
-        } else {
-            (NodeType::Smartphone, BATTERY_FULL_SMARTPHONE, 40.0)
-        };
+fn parse_disaster(value: &str) -> Result<DisasterRegion, String> {
+    Ok(match value.split_once(':') {
+        Some(("south", y)) => DisasterRegion::South {
+            y_max: y.parse().map_err(|_| format!("invalid disaster line '{}'", y))?,
+        },
+        Some(("circle", spec)) => {
+            let parts: Vec<f64> = spec.split(':')
+                .map(|part| part.parse().map_err(|_| format!("invalid disaster circle '{}'", spec)))
+                .collect::<Result<_, _>>()?;
+            match parts[..] {
+                [x, y, radius] => DisasterRegion::Circle { x, y, radius },
+                _ => return Err(format!("invalid disaster circle '{}' (expected <x>:<y>:<r>)", spec)),
+            }
+        }
+        Some(("fraction", f)) => DisasterRegion::Fraction { fraction: parse_probability(f)? },
+        Some(("hubs", n)) => DisasterRegion::TargetHubs {
+            count: n.parse().map_err(|_| format!("invalid hub count '{}'", n))?,
+        },
+        _ => return Err(format!("invalid disaster region '{}'", value)),
+    })
+}
 
-        let x = rng.random_range(0.0..200.0);
-        let y = rng.random_range(0.0..200.0);
-        
-        // Map to Nice, France (Approx 43.7102, 7.2620)
-        // Scale: 200 units = ~0.02 degrees (~2km)
-        let lat = 43.70 + (y * 0.0001);
-        let lon = 7.25 + (x * 0.0001);
-
-        Node {
-            id,
-            position: (x, y),
-            lat,
-            lon,
-            is_active: true,
-            peers: Vec::new(),
-            node_type,
-            battery_level: battery,
-            transmission_range: range,
-            wallet: Wallet {
-                address: format!("0x{:04x}...{:04x}", rng.random_range(0..65535), id),
-                balance_token: 0.0,
-                balance_usdc: 0.0,
-            },
+// Command-line options: the simulation config plus what to run and print
+struct Cli {
+    config: SimConfig,
+    weighted_split: Option<usize>, // Also benchmark WeightedSplit { k }
+    fair_swarm: bool, // Also benchmark FairSwarm, e.g. to compare its battery CoV with Swarm's
+    log_format: LogFormat,
+    table_format: TableFormat, // How the comparison table is printed
+    output: Option<String>, // Log path instead of simulation_log.<ext>
+    results: Option<String>, // Write the comparison as BenchmarkResult JSON here
+    #[cfg(feature = "tokenomics")]
+    wallets_out: Option<String>, // Final wallets of the exported run, as JSON
+    mode: Option<SimMode>, // None = run all modes and compare
+    modes: Option<Vec<SimMode>>, // Scenario's comparison set, instead of Flooding vs Swarm
+    baseline: SimMode, // Column the comparison is relative to
+    replay: Option<String>, // Re-encode this log in `log_format` instead of simulating
+    ttl_sweep: Option<(u32, u32)>, // Inclusive TTL range to sweep
+    bs_sweep: Option<(f64, f64, f64)>, // Base-station ratios start:step:end to sweep
+    sweep_target: f64, // Delivery ratio the base-station sweep stops at
+    repeat: Option<u64>, // Independent runs per mode, seeds seed..seed+N
+    jitter_runs: Option<u64>, // Same seed, jitter seeds 0..N: how much the geometry matters
+    scale_test: Option<Vec<u32>>, // Node counts to benchmark at constant density
+    hotspots: usize, // Busiest relays to list per mode
+    rate_window: Option<i32>, // Print the delivery-rate series in windows of this many steps
+    dry_run: bool, // Only build the topology and report on it
+    analyze_only: bool, // Full static graph analysis, no steps and no energy
+    interactive: bool, // Step one mode by hand from stdin
+    timing: bool, // Report wall-clock time per phase
+    metrics_stream: bool, // Print a StepMetrics JSON line per step as runs go
+}
+
+impl Cli {
+    fn export(&self) -> LogExport {
+        let path = self.output.clone().unwrap_or_else(|| self.log_format.default_path());
+        LogExport { format: self.log_format, path }
+    }
+}
+
+fn parse_args() -> Result<Cli, String> {
+    let raw: Vec<String> = std::env::args().skip(1).collect();
+    // The scenario file is the starting point; every other flag overrides it
+    let scenario = match raw.iter().position(|arg| arg == "--scenario") {
+        Some(i) => Some(Scenario::load(raw.get(i + 1).ok_or("--scenario needs a TOML file")?)?),
+        None => None,
+    };
+    let modes = scenario.as_ref().and_then(|s| s.modes.clone());
+    let mut config = scenario.map_or_else(SimConfig::default, |s| s.config);
+    let mut weighted_split = None;
+    let mut fair_swarm = false;
+    let mut log_format = None;
+    let mut table_format = TableFormat::Text;
+    let mut output = None;
+    let mut results = None;
+    #[cfg(feature = "tokenomics")]
+    let mut wallets_out = None;
+    let mut mode = None;
+    let mut baseline = SimMode::Flooding;
+    let mut replay = None;
+    let mut ttl_sweep = None;
+    let mut bs_sweep = None;
+    let mut sweep_target = 0.95;
+    let mut repeat = None;
+    let mut jitter_runs = None;
+    let mut scale_test = None;
+    let mut hotspots = 5;
+    let mut rate_window = None;
+    let mut dry_run = false;
+    let mut analyze_only = false;
+    let mut interactive = false;
+    let mut timing = false;
+    let mut metrics_stream = false;
+    let mut args = raw.into_iter().peekable();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--seed" => {
+                let value = args.next().ok_or("--seed needs a value")?;
+                config.seed = Some(value.parse().map_err(|_| format!("invalid seed '{}'", value))?);
+            }
+            "--nodes" => {
+                let value = args.next().ok_or("--nodes needs a value")?;
+                config.node_count = value.parse().map_err(|_| format!("invalid node count '{}'", value))?;
+            }
+            "--steps" => {
+                let value = args.next().ok_or("--steps needs a value")?;
+                config.max_steps = value.parse().map_err(|_| format!("invalid step count '{}'", value))?;
+            }
+            "--run-until-empty" => config.run_until_empty = true,
+            "--traffic" => {
+                let value = args.next().ok_or("--traffic needs a value")?;
+                config.traffic_pattern = parse_traffic(&value)
+                    .ok_or_else(|| format!("invalid traffic pattern '{}'", value))?;
+            }
+            "--burst-once" => {
+                let value = args.next().ok_or("--burst-once needs a packet count")?;
+                let count = value.parse().map_err(|_| format!("invalid packet count '{}'", value))?;
+                config.traffic_pattern = TrafficPattern::Once { count };
+            }
+            "--target" => {
+                let value = args.next().ok_or("--target needs a value (<id>, nearest-bs or random)")?;
+                config.target = match value.as_str() {
+                    "last" => TargetSelection::LastNode,
+                    "nearest-bs" => TargetSelection::NearestBaseStation,
+                    "random" => TargetSelection::RandomPair,
+                    id => TargetSelection::ById(
+                        id.parse().map_err(|_| format!("invalid target '{}'", id))?,
+                    ),
+                };
+            }
+            "--oscillation-factor" => {
+                let value = args.next().ok_or("--oscillation-factor needs a value")?;
+                config.oscillation_factor = value.parse()
+                    .map_err(|_| format!("invalid oscillation factor '{}'", value))?;
+            }
+            "--placement" => {
+                let value = args.next().ok_or("--placement needs a value (uniform|grid|clustered:<n>:<spread>)")?;
+                let parts: Vec<&str> = value.split(':').collect();
+                config.placement = match parts.as_slice() {
+                    ["uniform"] => Placement::Uniform,
+                    ["grid"] => Placement::Grid,
+                    ["clustered", clusters, spread] => match (clusters.parse(), spread.parse()) {
+                        (Ok(clusters), Ok(spread)) => Placement::Clustered { clusters, spread },
+                        _ => return Err(format!("invalid cluster placement '{}'", value)),
+                    },
+                    _ => return Err(format!("invalid placement '{}'", value)),
+                };
+            }
+            "--bs-placement" => {
+                let value = args.next().ok_or("--bs-placement needs a value (same|uniform|edge|sites:<x>,<y>;...)")?;
+                config.bs_placement = match value.split_once(':') {
+                    None if value == "same" => BsPlacement::SameAsPhones,
+                    None if value == "uniform" => BsPlacement::Uniform,
+                    None if value == "edge" => BsPlacement::EdgeBiased,
+                    Some(("sites", list)) => BsPlacement::Sites(list.split(';')
+                        .map(|site| site.split_once(',').and_then(|(x, y)| Some((x.parse().ok()?, y.parse().ok()?))))
+                        .collect::<Option<_>>()
+                        .ok_or_else(|| format!("invalid base station sites '{}'", list))?),
+                    _ => return Err(format!("invalid base station placement '{}'", value)),
+                };
+            }
+            "--distance" => {
+                let value = args.next().ok_or("--distance needs a value (euclidean|manhattan)")?;
+                config.distance_metric = match value.as_str() {
+                    "euclidean" => DistanceMetric::Euclidean,
+                    "manhattan" => DistanceMetric::Manhattan,
+                    other => return Err(format!("invalid distance metric '{}'", other)),
+                };
+            }
+            "--transmission" => {
+                let value = args.next().ok_or("--transmission needs a value (broadcast|unicast)")?;
+                config.transmission_mode = match value.as_str() {
+                    "broadcast" => TransmissionMode::Broadcast,
+                    "unicast" => TransmissionMode::Unicast,
+                    other => return Err(format!("invalid transmission mode '{}'", other)),
+                };
+            }
+            "--sla-deadline" => {
+                let value = args.next().ok_or("--sla-deadline needs a value (steps)")?;
+                config.sla_deadline_steps = value.parse()
+                    .map_err(|_| format!("invalid SLA deadline '{}'", value))?;
+            }
+            "--sla-target" => {
+                let value = args.next().ok_or("--sla-target needs a value (0.0-1.0)")?;
+                config.sla_target = value.parse()
+                    .map_err(|_| format!("invalid SLA target '{}'", value))?;
+            }
+            "--churn" => {
+                let value = args.next().ok_or("--churn needs a probability")?;
+                config.churn_prob = parse_probability(&value)?;
+            }
+            "--recovery" => {
+                let value = args.next().ok_or("--recovery needs a probability")?;
+                config.recovery_prob = parse_probability(&value)?;
+            }
+            "--survival-threshold" => {
+                let value = args.next().ok_or("--survival-threshold needs a value (0.0-1.0)")?;
+                config.survival_threshold = parse_probability(&value)?;
+            }
+            "--cooperation" => {
+                let value = args.next().ok_or("--cooperation needs a probability")?;
+                config.cooperation_prob = parse_probability(&value)?;
+            }
+            "--duty-cycle" => {
+                let value = args.next().ok_or("--duty-cycle needs a fraction of steps awake (0.0-1.0]")?;
+                config.duty_cycle = parse_probability(&value)?;
+            }
+            "--relay-cutoff" => {
+                let value = args.next().ok_or("--relay-cutoff needs a battery fraction (0.0-1.0)")?;
+                config.relay_cutoff_battery = parse_probability(&value)? as f32;
+            }
+            "--roles" => {
+                let value = args.next().ok_or("--roles needs <relay>:<endpoint> ratios")?;
+                let (relay, endpoint) = value.split_once(':')
+                    .ok_or_else(|| format!("invalid roles '{}' (expected <relay>:<endpoint>)", value))?;
+                config.role_ratios = (parse_probability(relay)?, parse_probability(endpoint)?);
+            }
+            "--no-rewards" => config.relay_rewards = false,
+            "--halving-interval" => {
+                let value = args.next().ok_or("--halving-interval needs a step count")?;
+                config.halving_interval = Some(value.parse().map_err(|_| format!("invalid halving interval '{}'", value))?);
+            }
+            "--progress-reward" => {
+                let value = args.next().ok_or("--progress-reward needs a multiplier")?;
+                config.progress_reward = value.parse()
+                    .map_err(|_| format!("invalid progress reward '{}'", value))?;
+            }
+            "--premium" => {
+                let value = args.next().ok_or("--premium needs a value (USDC per relay)")?;
+                config.premium_per_relay = value.parse()
+                    .map_err(|_| format!("invalid premium '{}'", value))?;
+            }
+            "--scaled-payout" => config.payout_scaling = true,
+            "--disaster" => {
+                let value = args.next().ok_or(format!("--disaster needs a value ({})", DISASTER_SPECS))?;
+                config.disaster_region = parse_disaster(&value)?;
+            }
+            "--also-disaster" => {
+                let value = args.next().ok_or(format!("--also-disaster needs a value ({})", DISASTER_SPECS))?;
+                config.extra_disaster_regions.push(parse_disaster(&value)?);
+            }
+            "--geo" => {
+                let value = args.next().ok_or("--geo needs <lat>:<lon>[:<degrees per unit>]")?;
+                let parts: Vec<f64> = value.split(':')
+                    .map(|part| part.parse().map_err(|_| format!("invalid projection '{}'", value)))
+                    .collect::<Result<_, _>>()?;
+                let default = GeoProjection::default();
+                config.projection = match parts[..] {
+                    [origin_lat, origin_lon] => GeoProjection { origin_lat, origin_lon, ..default },
+                    [origin_lat, origin_lon, scale] => GeoProjection { origin_lat, origin_lon, scale },
+                    _ => return Err(format!("invalid projection '{}'", value)),
+                };
+            }
+            "--hardened-bs" => config.disaster_spares_base_stations = true,
+            "--harden" => {
+                let value = args.next().ok_or("--harden needs node ids (e.g. 3,7,12)")?;
+                for id in value.split(',') {
+                    config.hardened_nodes.insert(id.trim().parse().map_err(|_| format!("invalid node id '{}'", id))?);
+                }
+            }
+            "--forward-progress-only" => config.forward_progress_only = true,
+            "--flood-suppression" => config.flood_suppression = true,
+            "--suppression-window" => {
+                let value = args.next().ok_or("--suppression-window needs a step count")?;
+                config.suppression_window = Some(value.parse().map_err(|_| format!("invalid suppression window '{}'", value))?);
+                config.flood_suppression = true;
+            }
+            "--backhaul" => config.base_station_backhaul = true,
+            "--carry" => {
+                let value = args.next().ok_or("--carry needs a buffer size[:<max hold steps>]")?;
+                let (buffer, hold) = match value.split_once(':') {
+                    Some((buffer, hold)) => (buffer, Some(hold)),
+                    None => (value.as_str(), None),
+                };
+                config.carry_buffer = Some(buffer.parse().map_err(|_| format!("invalid carry buffer '{}'", buffer))?);
+                if let Some(hold) = hold {
+                    config.carry_max_hold = hold.parse().map_err(|_| format!("invalid carry hold '{}'", hold))?;
+                }
+            }
+            "--hello-interval" => {
+                let value = args.next().ok_or("--hello-interval needs a step count")?;
+                config.hello_interval = Some(value.parse().map_err(|_| format!("invalid hello interval '{}'", value))?);
+            }
+            "--bs-ttl-refresh" => config.basestation_ttl_refresh = true,
+            "--backbone" => config.backbone = true,
+            "--interference" => config.interference = true,
+            "--interference-factor" => {
+                let value = args.next().ok_or("--interference-factor needs a value")?;
+                config.interference_factor = value.parse()
+                    .map_err(|_| format!("invalid interference factor '{}'", value))?;
+            }
+            "--link-loss" => {
+                let value = args.next().ok_or("--link-loss needs a probability (drop rate at the range edge)")?;
+                config.link_loss = parse_probability(&value)?;
+            }
+            "--weak-link-cost" => {
+                let value = args.next().ok_or("--weak-link-cost needs a value (extra energy at the range edge)")?;
+                config.weak_link_cost = value.parse().map_err(|_| format!("invalid weak link cost '{}'", value))?;
+            }
+            "--battery-range" => {
+                let value = args.next().ok_or("--battery-range needs a value (<min>:<max>)")?;
+                let parsed = value.split_once(':')
+                    .and_then(|(min, max)| Some((min.parse().ok()?, max.parse().ok()?)));
+                config.battery_range = match parsed {
+                    Some((min, max)) if min > 0.0 && min <= max => (min, max),
+                    _ => return Err(format!("invalid battery range '{}'", value)),
+                };
+            }
+            "--packet-size" => {
+                let value = args.next().ok_or("--packet-size needs a value (<bytes> or <min>:<max>)")?;
+                let (min, max) = value.split_once(':').unwrap_or((&value, &value));
+                config.packet_size_range = match (min.parse(), max.parse()) {
+                    (Ok(min), Ok(max)) if min <= max => (min, max),
+                    _ => return Err(format!("invalid packet size '{}'", value)),
+                };
+            }
+            "--energy-per-byte" => {
+                let value = args.next().ok_or("--energy-per-byte needs a value")?;
+                config.energy_per_byte = value.parse()
+                    .map_err(|_| format!("invalid energy per byte '{}'", value))?;
+            }
+            "--bs-activation" => {
+                let value = args.next().ok_or("--bs-activation needs a range (<first>:<last>)")?;
+                let range = value.split_once(':')
+                    .and_then(|(first, last)| Some((first.parse::<i32>().ok()?, last.parse::<i32>().ok()?)))
+                    .filter(|(first, last)| first <= last)
+                    .ok_or_else(|| format!("invalid activation range '{}'", value))?;
+                config.bs_activation_range = Some(range);
+            }
+            "--bs-harvest" => {
+                let value = args.next().ok_or("--bs-harvest needs a value (energy per step)")?;
+                config.bs_harvest_rate = value.parse()
+                    .map_err(|_| format!("invalid harvest rate '{}'", value))?;
+            }
+            "--phone-harvest" => {
+                let value = args.next().ok_or("--phone-harvest needs a value (energy per step)")?;
+                config.phone_harvest_rate = value.parse()
+                    .map_err(|_| format!("invalid harvest rate '{}'", value))?;
+            }
+            "--step-budget" => {
+                let value = args.next().ok_or("--step-budget needs a value (energy per node per step)")?;
+                config.step_energy_budget = Some(value.parse().map_err(|_| format!("invalid step budget '{}'", value))?);
+            }
+            "--global-tx-budget" => {
+                let value = args.next().ok_or("--global-tx-budget needs a value (transmissions per step, mesh-wide)")?;
+                config.global_tx_budget_per_step = Some(value.parse().map_err(|_| format!("invalid global TX budget '{}'", value))?);
+            }
+            "--max-queue" => {
+                let value = args.next().ok_or("--max-queue needs a value (queued packets, mesh-wide)")?;
+                config.max_queue_size = Some(value.parse().map_err(|_| format!("invalid queue size '{}'", value))?);
+            }
+            "--retry" => {
+                let value = args.next().ok_or("--retry needs <after>[:<max retries>]")?;
+                let (after, max) = value.split_once(':').unwrap_or((&value, "3"));
+                config.retry_after = Some(after.parse().map_err(|_| format!("invalid retry delay '{}'", after))?);
+                config.max_retries = max.parse().map_err(|_| format!("invalid retry count '{}'", max))?;
+            }
+            "--importance-decay" => {
+                let value = args.next().ok_or("--importance-decay needs <factor>[:<floor>]")?;
+                let (decay, floor) = value.split_once(':').unwrap_or((&value, ""));
+                config.importance_decay = Some(decay.parse().map_err(|_| format!("invalid importance decay '{}'", decay))?);
+                if !floor.is_empty() {
+                    config.importance_floor = floor.parse().map_err(|_| format!("invalid importance floor '{}'", floor))?;
+                }
+            }
+            "--max-hint-age" => {
+                let value = args.next().ok_or("--max-hint-age needs a value (steps)")?;
+                config.max_hint_age = Some(value.parse().map_err(|_| format!("invalid hint age '{}'", value))?);
+            }
+            "--weighted-split" => {
+                let value = args.next().ok_or("--weighted-split needs a value (k)")?;
+                weighted_split = Some(value.parse().map_err(|_| format!("invalid k '{}'", value))?);
+            }
+            "--fair-swarm" => fair_swarm = true,
+            "--fair-decay" => {
+                let value = args.next().ok_or("--fair-decay needs a fraction (0.0-1.0)")?;
+                config.fair_load_decay = parse_probability(&value)?;
+            }
+            "--mode" => {
+                let value = args.next().ok_or("--mode needs a value (flooding|swarm|adaptive|fair-swarm|weighted:<k>|all)")?;
+                mode = match value.as_str() {
+                    "all" => None,
+                    other => Some(parse_mode(other).ok_or_else(|| format!("invalid mode '{}'", other))?),
+                };
+            }
+            "--baseline" => {
+                let value = args.next().ok_or("--baseline needs a mode (flooding|swarm|adaptive|fair-swarm|weighted:<k>)")?;
+                baseline = parse_mode(&value).ok_or_else(|| format!("invalid baseline mode '{}'", value))?;
+            }
+            "--table-format" => {
+                let value = args.next().ok_or("--table-format needs a value (text|markdown|csv)")?;
+                table_format = TableFormat::parse(&value).ok_or_else(|| format!("invalid table format '{}'", value))?;
+            }
+            "--log-format" => {
+                let value = args.next().ok_or("--log-format needs a value (json|msgpack|csv|jsonl)")?;
+                log_format = Some(match value.as_str() {
+                    "json" => LogFormat::Json,
+                    "msgpack" => LogFormat::MessagePack,
+                    "csv" => LogFormat::Csv,
+                    "jsonl" => LogFormat::Jsonl,
+                    other => return Err(format!("invalid log format '{}'", other)),
+                });
+            }
+            "--output" => output = Some(args.next().ok_or("--output needs a file path")?),
+            "--results" => results = Some(args.next().ok_or("--results needs a file path (e.g. benchmark_result.json)")?),
+            #[cfg(feature = "tokenomics")]
+            "--wallets-out" => wallets_out = Some(args.next().ok_or("--wallets-out needs a file path")?),
+            "--ttl" => {
+                let value = args.next().ok_or("--ttl needs a value (hops)")?;
+                config.ttl = value.parse().map_err(|_| format!("invalid TTL '{}'", value))?;
+            }
+            "--ttl-sweep" => {
+                let value = args.next().ok_or("--ttl-sweep needs a range (min:max)")?;
+                let range = value.split_once(':')
+                    .and_then(|(min, max)| Some((min.parse::<u32>().ok()?, max.parse::<u32>().ok()?)))
+                    .filter(|(min, max)| min <= max)
+                    .ok_or_else(|| format!("invalid TTL range '{}'", value))?;
+                ttl_sweep = Some(range);
+            }
+            "--bs-ratio" => {
+                let value = args.next().ok_or("--bs-ratio needs a fraction (0.0-1.0)")?;
+                config.bs_ratio = parse_probability(&value)?;
+            }
+            "--sweep-basestations" => {
+                let value = args.next().ok_or("--sweep-basestations needs a range (start:step:end)")?;
+                let parts: Vec<f64> = value.split(':').filter_map(|part| part.parse().ok()).collect();
+                bs_sweep = match parts[..] {
+                    [start, step, end] if step > 0.0 && (0.0..=end).contains(&start) && end <= 1.0 => Some((start, step, end)),
+                    _ => return Err(format!("invalid base-station range '{}' (expected start:step:end within 0.0-1.0)", value)),
+                };
+            }
+            "--sweep-target" => {
+                let value = args.next().ok_or("--sweep-target needs a delivery ratio (0.0-1.0)")?;
+                sweep_target = parse_probability(&value)?;
+            }
+            "--repeat" => {
+                let value = args.next().ok_or("--repeat needs a run count")?;
+                repeat = Some(value.parse().ok().filter(|&n| n > 0)
+                    .ok_or_else(|| format!("invalid run count '{}'", value))?);
+            }
+            "--jitter" => {
+                let value = args.next().ok_or("--jitter needs a standard deviation (grid units)")?;
+                config.position_jitter_stddev = value.parse()
+                    .map_err(|_| format!("invalid jitter '{}'", value))?;
+            }
+            "--jitter-seed" => {
+                let value = args.next().ok_or("--jitter-seed needs a value")?;
+                config.jitter_seed = value.parse().map_err(|_| format!("invalid jitter seed '{}'", value))?;
+            }
+            "--jitter-runs" => {
+                let value = args.next().ok_or("--jitter-runs needs a run count")?;
+                jitter_runs = Some(value.parse().ok().filter(|&n| n > 0)
+                    .ok_or_else(|| format!("invalid run count '{}'", value))?);
+            }
+            "--scale-test" => {
+                // The node counts are optional: a following flag means "use the defaults"
+                let counts = match args.next_if(|next| !next.starts_with("--")) {
+                    Some(value) => value.split(',')
+                        .map(|count| count.trim().parse().ok().filter(|&n| n >= 2)
+                            .ok_or_else(|| format!("invalid node count '{}'", count)))
+                        .collect::<Result<Vec<u32>, String>>()?,
+                    None => SCALE_TEST_COUNTS.to_vec(),
+                };
+                scale_test = Some(counts);
+            }
+            "--hotspots" => {
+                let value = args.next().ok_or("--hotspots needs a count")?;
+                hotspots = value.parse().map_err(|_| format!("invalid hotspot count '{}'", value))?;
+            }
+            "--rate-window" => {
+                let value = args.next().ok_or("--rate-window needs a value (steps)")?;
+                rate_window = Some(value.parse().ok().filter(|&w| w > 0)
+                    .ok_or_else(|| format!("invalid rate window '{}'", value))?);
+            }
+            "--scenario" => {
+                args.next(); // Already loaded
+            }
+            "--dry-run" => dry_run = true,
+            "--analyze-only" => analyze_only = true,
+            "--interactive" => interactive = true,
+            "--timing" => timing = true,
+            "--metrics-stream" => metrics_stream = true,
+            "--replay" => replay = Some(args.next().ok_or("--replay needs a log file")?),
+            other => return Err(format!("unknown argument '{}'", other)),
         }
     }
+    // Without --log-format, a recognised --output extension picks the format
+    let log_format = log_format.or_else(|| output.as_deref().and_then(LogFormat::from_path)).unwrap_or_default();
+    Ok(Cli {
+        config,
+        weighted_split,
+        fair_swarm,
+        log_format,
+        table_format,
+        output,
+        results,
+        #[cfg(feature = "tokenomics")]
+        wallets_out,
+        mode,
+        modes,
+        baseline,
+        replay,
+        ttl_sweep,
+        bs_sweep,
+        sweep_target,
+        repeat,
+        jitter_runs,
+        scale_test,
+        hotspots,
+        rate_window,
+        dry_run,
+        analyze_only,
+        interactive,
+        timing,
+        metrics_stream,
+    })
+}
+
+fn fmt_latency(stats: &SimStats) -> String {
+    match stats.avg_latency() {
+        Some(avg) => format!("{:.2}", avg),
+        None => "N/A".to_string(),
+    }
+}
 
-    fn distance_to(&self, other: &Node) -> f64 {
-        let dx = self.position.0 - other.position.0;
-        let dy = self.position.1 - other.position.1;
-        (dx * dx + dy * dy).sqrt()
+// "p50/p95/p99"; flagged when there are too few deliveries for a meaningful p99
+fn fmt_percentiles(stats: &SimStats) -> String {
+    let values: Option<Vec<u32>> = [50.0, 95.0, 99.0].iter().map(|&p| stats.latency_percentile(p)).collect();
+    match values {
+        Some(v) if stats.latencies.len() < 100 => format!("{}/{}/{} (n={})", v[0], v[1], v[2], stats.latencies.len()),
+        Some(v) => format!("{}/{}/{}", v[0], v[1], v[2]),
+        None => "N/A".to_string(),
     }
-    
-    fn consume_battery(&mut self, cost: f32) {
-        if self.node_type == NodeType::Smartphone {
-            self.battery_level = (self.battery_level - cost).max(0.0);
-            if self.battery_level <= 0.0 {
-                self.is_active = false;
-            }
+}
+
+fn fmt_inter_delivery(stats: &SimStats) -> String {
+    stats.mean_inter_delivery().map_or("n/a".to_string(), |gap| format!("{:.2}", gap))
+}
+
+fn fmt_stretch(stats: &SimStats) -> String {
+    match (stats.avg_path_stretch, stats.optimal_hops) {
+        (Some(ratio), Some(optimal)) => format!("{:.2}x (opt {})", ratio, optimal),
+        _ => "N/A".to_string(),
+    }
+}
+
+fn fmt_sla(stats: &SimStats) -> String {
+    format!("{:.1}% {}", stats.sla_fraction * 100.0, if stats.sla_met { "PASS" } else { "FAIL" })
+}
+
+fn fmt_deaths(stats: &SimStats) -> String {
+    format!("{}/{}/{}", stats.deaths_disaster, stats.deaths_battery, stats.deaths_churn)
+}
+
+#[cfg(feature = "tokenomics")]
+fn fmt_solvency(stats: &SimStats) -> String {
+    match stats.solvency_ratio() {
+        Some(ratio) if ratio < 1.0 => format!("insolvent {:.2}x", ratio),
+        Some(ratio) => format!("{:.2}x", ratio),
+        None => "n/a".to_string(),
+    }
+}
+
+fn fmt_never_relayed(stats: &SimStats) -> String {
+    format!("{}/{} ({:.0}%)", stats.never_relayed, stats.relay_candidates, stats.never_relayed_fraction() * 100.0)
+}
+
+fn fmt_relay_hops(stats: &SimStats) -> String {
+    let share = stats.base_station_hop_share().map_or("N/A".to_string(), |share| format!("{:.0}%", share * 100.0));
+    format!("{} / {} ({} BS)", stats.relay_hops_base_station, stats.relay_hops_smartphone, share)
+}
+
+fn fmt_delivery_split(stats: &SimStats) -> String {
+    format!("{} / {}", stats.delivered_via_backhaul, stats.delivered_p2p)
+}
+
+fn fmt_target(stats: &SimStats) -> String {
+    match stats.target_lost_step {
+        Some(step) => format!("node {} lost@{}", stats.target_id, step),
+        None => format!("node {} alive", stats.target_id),
+    }
+}
+
+// Graph before -> after the (first) disaster
+fn fmt_coverage(coverage: Option<f64>) -> String {
+    coverage.map_or("N/A".to_string(), |share| format!("{:.0}%", share * 100.0))
+}
+
+fn fmt_impact(stats: &SimStats, value: fn(&DisasterImpact) -> (String, String)) -> String {
+    stats.disaster_impacts.first().map_or("N/A".to_string(), |impact| {
+        let (before, after) = value(impact);
+        format!("{} -> {}", before, after)
+    })
+}
+
+fn fmt_critical(relays: Option<usize>) -> String {
+    relays.map_or("cut off".to_string(), |count| count.to_string())
+}
+
+// Deliveries that only drained the queue once the source was gone
+fn fmt_drained(stats: &SimStats) -> String {
+    match stats.source_lost_step {
+        Some(step) => format!("{} after @{}", stats.drained_deliveries, step),
+        None => "source alive".to_string(),
+    }
+}
+
+fn mode_label(mode: SimMode) -> String {
+    match mode {
+        SimMode::Flooding => "Flooding (Old)".to_string(),
+        SimMode::Swarm => "Swarm (Unicorn)".to_string(),
+        SimMode::WeightedSplit { k } => format!("Split k={}", k),
+        SimMode::Adaptive => "Adaptive".to_string(),
+        SimMode::FairSwarm => "Fair Swarm".to_string(),
+    }
+}
+
+// One column per mode; energy savings and battery extension are relative to `baseline`
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TableFormat {
+    Text, // Aligned columns for the terminal
+    Markdown,
+    Csv,
+}
+
+impl TableFormat {
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "text" => Some(TableFormat::Text),
+            "markdown" | "md" => Some(TableFormat::Markdown),
+            "csv" => Some(TableFormat::Csv),
+            _ => None,
         }
     }
 }
 
-struct SimStats {
-    total_energy: f32,
-    success_packets: u32,
-    total_hops: u32,
+// A metric per row, one column per run
+struct Table {
+    corner: String, // Heading of the label column
+    header: Vec<String>,
+    rows: Vec<(String, Vec<String>)>,
 }
 
-fn run_simulation(mode: SimMode, export_logs: bool) -> SimStats {
-    println!("\n▶️ RUNNING SIMULATION: {:?}", mode);
-    
-    // Hardcoded seed logic is tricky in simple Rust without specific crates, 
-    // but we'll re-generate nodes similarly to keep it fair-ish.
-    let node_count = 60;
-    let mut nodes: Vec<Node> = (0..node_count).map(|i| Node::new(i)).collect();
-
-    // Rebuild Adjacency
-    let mut adjacency: HashMap<u32, Vec<u32>> = HashMap::new();
-    for i in 0..node_count { adjacency.insert(i as u32, Vec::new()); }
-    for i in 0..nodes.len() {
-        for j in 0..nodes.len() {
-            if i == j { continue; }
-            if nodes[i].distance_to(&nodes[j]) <= nodes[i].transmission_range {
-                adjacency.get_mut(&(i as u32)).unwrap().push(j as u32);
+impl Table {
+    fn render(&self, format: TableFormat) -> String {
+        let mut lines = Vec::new();
+        match format {
+            TableFormat::Text => {
+                let line = |label: &str, cells: &[String]| {
+                    let cells: Vec<String> = cells.iter().map(|cell| format!("{:>15}", cell)).collect();
+                    format!("{:<22} | {}", label, cells.join(" | "))
+                };
+                lines.push(line(&self.corner, &self.header));
+                lines.push(format!("{}|{}", "-".repeat(23), vec!["-".repeat(17); self.header.len()].join("|")));
+                lines.extend(self.rows.iter().map(|(label, cells)| line(label, cells)));
+            }
+            TableFormat::Markdown => {
+                // Pipes inside a cell would split it; indentation would be eaten, so sub-rows get a marker
+                let escape = |cell: &str| cell.replace('|', "\\|");
+                let line = |label: &str, cells: &[String]| {
+                    let cells: Vec<String> = cells.iter().map(|cell| escape(cell)).collect();
+                    format!("| {} | {} |", label, cells.join(" | "))
+                };
+                lines.push(line(&self.corner, &self.header));
+                lines.push(format!("| --- |{}", " ---: |".repeat(self.header.len())));
+                lines.extend(self.rows.iter().map(|(label, cells)| {
+                    let trimmed = label.trim_start();
+                    let label = if trimmed.len() < label.len() { format!("↳ {}", escape(trimmed)) } else { escape(label) };
+                    line(&label, cells)
+                }));
+            }
+            TableFormat::Csv => {
+                let field = |cell: &str| {
+                    let cell = cell.trim();
+                    if cell.contains([',', '"', '\n']) { format!("\"{}\"", cell.replace('"', "\"\"")) } else { cell.to_string() }
+                };
+                let line = |label: &str, cells: &[String]| {
+                    std::iter::once(field(label)).chain(cells.iter().map(|cell| field(cell))).collect::<Vec<_>>().join(",")
+                };
+                lines.push(line(&self.corner, &self.header));
+                lines.extend(self.rows.iter().map(|(label, cells)| line(label, cells)));
             }
         }
+        lines.join("\n")
+    }
+}
+
+fn print_comparison(config: &SimConfig, runs: &[(SimMode, SimStats)], baseline: Option<SimMode>, format: TableFormat) {
+    narrate!("{}", stats_table(config, runs, baseline).render(format));
+}
+
+// A single --mode run: just its stats, one per line
+fn print_stats(config: &SimConfig, mode: SimMode, stats: &SimStats) {
+    for (label, cells) in stats_table(config, &[(mode, stats.clone())], None).rows {
+        narrate!("{:<24}{}", format!("{}:", label), cells[0]);
+    }
+}
+
+// One row per stat, one column per run
+fn stats_table(config: &SimConfig, runs: &[(SimMode, SimStats)], baseline: Option<SimMode>) -> Table {
+    let mut table = Table { corner: "Metric".to_string(), header: runs.iter().map(|(mode, _)| mode_label(*mode)).collect(), rows: Vec::new() };
+    let mut row = |label: &str, cell: &dyn Fn(&SimStats) -> String| {
+        table.rows.push((label.to_string(), runs.iter().map(|(_, stats)| cell(stats)).collect()));
+    };
+
+    row("Total Energy Consumed", &|s| format!("{:.1}", s.total_energy));
+    row("  Idle Energy", &|s| format!("{:.1}", s.idle_energy));
+    row("  TX Energy", &|s| format!("{:.1}", s.tx_energy));
+    row("  RX Energy", &|s| format!("{:.1}", s.rx_energy));
+    if config.hello_interval.is_some() {
+        row("  Control Energy", &|s| format!("{:.1}", s.control_energy));
+    }
+    row("Phone Energy Left", &|s| format!("{:.1}", s.phone_energy_remaining));
+    row("Wasted (undelivered)", &|s| match s.waste_ratio() {
+        Some(ratio) => format!("{:.1} ({:.2}x)", s.wasted_energy, ratio),
+        None => format!("{:.1}", s.wasted_energy),
+    });
+    let baseline_stats = baseline.and_then(|b| runs.iter().find(|(mode, _)| *mode == b)).map(|(_, stats)| stats);
+    if let Some(base) = baseline_stats {
+        row("Energy Saved", &|s| s.energy_saved_vs(base).map_or("N/A".to_string(), |saved| format!("{:.1}%", saved)));
+    }
+    row("Packets Delivered", &|s| s.success_packets.to_string());
+    if config.base_station_backhaul {
+        row("Delivered BS / P2P", &fmt_delivery_split);
+    }
+    if config.carry_buffer.is_some() {
+        row("Carried (buffered)", &|s| format!("{} ({} full)", s.carried_packets, s.carry_overflow));
+        row("Delivered After Carry", &|s| s.carried_deliveries.to_string());
     }
-    for node in &mut nodes {
-        if let Some(peers) = adjacency.get(&node.id) {
-            node.peers = peers.clone();
+    if config.basestation_ttl_refresh {
+        row("TTL Refreshes", &|s| format!("{} ({} past TTL)", s.ttl_refreshes, s.beyond_ttl_deliveries));
+    }
+    if config.backbone {
+        row("Backbone Transfers", &|s| s.backbone_transfers.to_string());
+    }
+    row("Deliveries per kJ", &|s| s.deliveries_per_kj().map_or("N/A".to_string(), |rate| format!("{:.2}", rate)));
+    row("Delivered Bytes", &|s| s.delivered_bytes.to_string());
+    row("Bytes per J", &|s| s.bytes_per_joule().map_or("N/A".to_string(), |rate| format!("{:.3}", rate)));
+    row("Total Hops (Traffic)", &|s| s.total_hops.to_string());
+    row("Peak Queue Depth", &|s| s.max_queue_depth.to_string());
+    // Lots left over: max_steps (or --run-until-empty) cut the run short, not the mesh
+    row("In Flight at End", &|s| format!("{} ({} undelivered msgs)", s.packets_in_flight_at_end, s.lost_in_flight));
+    row("Redundant Deliveries", &|s| s.redundant_transmissions.to_string());
+    row("Oscillating Messages", &|s| s.oscillating_packets.to_string());
+    row("Avg Latency (steps)", &fmt_latency);
+    row("Latency p50/p95/p99", &fmt_percentiles);
+    row("Inter-Delivery (steps)", &fmt_inter_delivery);
+    row("Path Stretch", &fmt_stretch);
+    row("Distinct Paths / Msg", &|s| s.avg_distinct_paths.map_or("N/A".to_string(), |paths| format!("{:.2}", paths)));
+    row(&format!("SLA <= {} steps", config.sla_deadline_steps), &fmt_sla);
+    row("Deaths (dis/bat/churn)", &fmt_deaths);
+    row("Avg Degree (disaster)", &|s| fmt_impact(s, |i| (format!("{:.2}", i.degree_before), format!("{:.2}", i.degree_after))));
+    row("BS Coverage (disaster)", &|s| fmt_impact(s, |i| (fmt_coverage(i.coverage_before), fmt_coverage(i.coverage_after))));
+    row("Largest CC (disaster)", &|s| fmt_impact(s, |i| (i.largest_before.to_string(), i.largest_after.to_string())));
+    // Few critical relays = fragile routing; they're the ones worth hardening
+    row("Critical Relays (dis)", &|s| fmt_impact(s, |i| (fmt_critical(i.critical_before), fmt_critical(i.critical_after))));
+    row("Never Relayed", &fmt_never_relayed);
+    row("Relay Hops BS / Phone", &fmt_relay_hops);
+    row("Battery CoV mean/peak", &|s| s.battery_cov_summary()
+        .map_or("N/A".to_string(), |(mean, peak)| format!("{:.3}/{:.3}", mean, peak)));
+    row("Target Reachability", &fmt_target);
+    row("Source Drainage", &fmt_drained);
+    row("Network Death (step)", &|s| s.network_death_step.map_or("alive".to_string(), |step| step.to_string()));
+    #[cfg(feature = "tokenomics")]
+    row("Insurance Solvency", &fmt_solvency);
+    #[cfg(feature = "tokenomics")]
+    if config.halving_interval.is_some() {
+        row("Tokens Minted", &|s| format!("{:.1}", s.tokens_minted));
+        row("Final Relay Reward", &|s| format!("{}", config.relay_reward(s.steps_run)));
+    }
+    #[cfg(feature = "tokenomics")]
+    row("Tokens / Delivered Msg", &|s| s.tokens_per_delivered_message()
+        .map_or("N/A".to_string(), |tokens| format!("{:.1}", tokens)));
+    if config.interference {
+        row("Interference Losses", &|s| s.interference_losses.to_string());
+    }
+    if config.link_loss > 0.0 {
+        row("Weak Link Losses", &|s| s.link_losses.to_string());
+    }
+    if config.cooperation_prob < 1.0 {
+        row("Refused Forwards", &|s| s.refused_forwards.to_string());
+    }
+    if config.duty_cycle < 1.0 {
+        row("Asleep Holds", &|s| s.sleep_deferrals.to_string());
+    }
+    if config.relay_cutoff_battery > 0.0 {
+        row("Low-Battery Refusals", &|s| format!("{} ({} lost)", s.cutoff_refusals, s.cutoff_lost));
+    }
+    if config.bs_activation_range.is_some() {
+        row("Via Late Base Station", &|s| s.late_bs_deliveries.to_string());
+    }
+    if config.forward_progress_only {
+        row("Backward Skips", &|s| s.backward_skips.to_string());
+    }
+    if config.flood_suppression {
+        row("Suppressed Duplicates", &|s| s.suppressed_duplicates.to_string());
+    }
+    if matches!(config.traffic_pattern, TrafficPattern::Once { .. }) {
+        row("Burst Settled (step)", &|s| s.settled_step.map_or("unsettled".to_string(), |step| step.to_string()));
+    }
+    if config.retry_after.is_some() {
+        row("Retries Sent", &|s| s.retries.to_string());
+        row("Delivered After Retry", &|s| s.delivered_after_retry.to_string());
+        if config.importance_decay.is_some() {
+            row("Retries Abandoned", &|s| s.abandoned_messages.to_string());
         }
     }
+    if config.step_energy_budget.is_some() {
+        row("Deferred (budget)", &|s| s.deferred_packets.to_string());
+    }
+    if config.max_queue_size.is_some() {
+        row("Dropped (queue full)", &|s| s.dropped_overflow.to_string());
+    }
+    if config.global_tx_budget_per_step.is_some() {
+        row("Deferred (spectrum)", &|s| s.spectrum_deferrals.to_string());
+    }
+    if runs.iter().any(|(mode, _)| *mode == SimMode::Adaptive) {
+        row("ACKs Lost (one-way)", &|s| s.acks_lost.to_string());
+    }
+    if let Some(base) = baseline_stats {
+        row("Battery Life Extension", &|s| s.battery_extension_vs(base).map_or("N/A".to_string(), |ext| format!("{:.1}x", ext)));
+    }
+    table
+}
+
+// Relays that carry most delivered paths are single points of failure
+fn print_hotspots(runs: &[(SimMode, SimStats)], top: usize) {
+    if top == 0 {
+        return;
+    }
+    narrate!("\n=== 🔥 Hotspot Relays (top {}) ===", top);
+    for (mode, stats) in runs {
+        let listed: Vec<String> = stats.hotspots.iter().take(top)
+            .map(|&(id, count)| format!("#{} {:.0}%", id, count as f64 / stats.success_packets as f64 * 100.0))
+            .collect();
+        let listed = if listed.is_empty() { "none".to_string() } else { listed.join(", ") };
+        narrate!("{:<22} | {}", mode_label(*mode), listed);
+    }
+}
 
-    let start_node_id = 0;
-    let target_node_id = node_count - 1;
-    let mut packet_queue: VecDeque<Packet> = VecDeque::new();
-    
-    let mut rng = rand::rng();
-    let max_steps = 40;
-    let mut total_energy_consumed: f32 = 0.0;
-    let mut successful_packets = 0;
-    let mut total_hops = 0;
-    let mut disaster_triggered = false;
-    let mut oracle_alert_sent = false;
-
-    // For visualization logs
-    let mut sim_logs: Vec<SimLog> = Vec::new();
-
-    for step in 1..=max_steps {
-        let mut current_step_events: Vec<String> = Vec::new();
-
-        // 1. Disaster (Only in Swarm mode for demo, or both? Let's do both to show resilience difference)
-        if step == DISASTER_STEP {
-            current_step_events.push("DISASTER_START".to_string());
-            println!("⚠️  ALERT: DISASTER OCCURRED!");
-            let mut destroyed_count = 0;
-            for node in &mut nodes {
-                // South Area (y < 80.0)
-                if node.position.1 < 80.0 && node.is_active {
-                    node.is_active = false;
-                    node.battery_level = 0.0;
-                    destroyed_count += 1;
-                }
-            }
-            println!("🔥 {} nodes destroyed.", destroyed_count);
-            disaster_triggered = true;
+// What became of every message that never arrived
+fn print_drop_reasons(runs: &[(SimMode, SimStats)]) {
+    narrate!("\n=== 🪦 Lost Messages by Cause ===");
+    for (mode, stats) in runs {
+        let mut listed: Vec<String> = stats.drop_reasons.iter()
+            .map(|(reason, count)| format!("{} {}", reason.label(), count))
+            .collect();
+        if stats.lost_in_flight > 0 {
+            listed.push(format!("still in flight {}", stats.lost_in_flight));
         }
+        let listed = if listed.is_empty() { "none".to_string() } else { listed.join(", ") };
+        narrate!("{:<22} | {}", mode_label(*mode), listed);
+    }
+}
+
+// Deliveries per step over time: the dip at the disaster and the recovery after it
+fn print_delivery_rate(runs: &[(SimMode, SimStats)], window: i32) {
+    narrate!("\n=== 📈 Delivery Rate (per step, {}-step windows) ===", window);
+    let header: Vec<String> = runs.iter().map(|(mode, _)| format!("{:>15}", mode_label(*mode))).collect();
+    narrate!("{:<22} | {}", "Steps", header.join(" | "));
+    let series: Vec<Vec<(i32, f64)>> = runs.iter().map(|(_, stats)| stats.delivery_rate(window)).collect();
+    let longest = series.iter().max_by_key(|rates| rates.len()).cloned().unwrap_or_default();
+    let last_step = runs.iter().map(|(_, stats)| stats.steps_run).max().unwrap_or(0);
+    for (i, (start, _)) in longest.iter().enumerate() {
+        let cells: Vec<String> = series.iter()
+            .map(|rates| rates.get(i).map_or(format!("{:>15}", "-"), |(_, rate)| format!("{:>15.2}", rate)))
+            .collect();
+        narrate!("{:<22} | {}", format!("{}..{}", start, (start + window - 1).min(last_step)), cells.join(" | "));
+    }
+}
+
+// Resolved config and topology sanity checks, without stepping
+fn dry_run(config: &SimConfig) {
+    narrate!("\n=== 🧪 DRY RUN ===");
+    narrate!("{:#?}", config);
+    let sim = Simulation::new(SimMode::Swarm, config);
+    let graph = sim.graph();
+    let nodes = sim.state().nodes;
+    let base_stations = nodes.iter().filter(|n| n.node_type == NodeType::BaseStation).count();
+    let avg_degree = graph.link_count() as f64 / graph.node_count() as f64;
+    let (source, target) = sim.endpoints();
+    narrate!("Nodes                  | {:>14}", nodes.len());
+    narrate!("Base Stations          | {:>14}", base_stations);
+    // Phones whose own short range reaches a base station (stations hear everyone; this is the uplink)
+    let phones: Vec<&Node> = nodes.iter().filter(|n| n.node_type == NodeType::Smartphone).collect();
+    let covered = phones.iter()
+        .filter(|phone| graph.neighbors(phone.id).iter().any(|&id| nodes[id as usize].node_type == NodeType::BaseStation))
+        .count();
+    if !phones.is_empty() {
+        narrate!("BS Uplink (phones)     | {:>13.1}%", covered as f64 / phones.len() as f64 * 100.0);
+    }
+    narrate!("Avg Degree (out)       | {:>14.2}", avg_degree);
+    narrate!("Connected Components   | {:>14}", graph.connected_components().len());
+    match graph.shortest_path(source, target) {
+        Some(path) => narrate!("Source -> Target       | {:>14}", format!("{} hops", path.len() - 1)),
+        None => narrate!("Source -> Target       | {:>14}", "UNREACHABLE"),
+    }
+}
+
+// Static topology study: everything the graph alone can say, without stepping
+fn analyze(config: &SimConfig) {
+    narrate!("\n=== 🔬 TOPOLOGY ANALYSIS ===");
+    let sim = Simulation::new(SimMode::Swarm, config);
+    let nodes = sim.state().nodes;
+    let graph = sim.graph().survivors(nodes); // Late base stations aren't up yet
+    let (source, target) = sim.endpoints();
+
+    let mut degrees: Vec<usize> = nodes.iter().filter(|n| n.is_active).map(|n| graph.degree(n.id)).collect();
+    degrees.sort_unstable();
+    let components = graph.connected_components();
+    let mut sizes: Vec<usize> = components.iter().map(Vec::len).collect();
+    sizes.sort_unstable_by(|a, b| b.cmp(a));
+    let sizes: Vec<String> = sizes.iter().take(5).map(usize::to_string).collect();
 
-        // 2. Oracle (Tokenomics)
-        if disaster_triggered && !oracle_alert_sent && mode == SimMode::Swarm {
-             // Calculate survival rate
-             let south_total = nodes.iter().filter(|n| n.position.1 < 80.0).count();
-             let south_active = nodes.iter().filter(|n| n.position.1 < 80.0 && n.is_active).count();
-             if south_total > 0 && south_active == 0 {
-                 println!("[ORACLE] 💸 INSURANCE TRIGGERED! Paying out USDC to victims...");
-                 oracle_alert_sent = true;
-                 current_step_events.push("ORACLE_PAYOUT".to_string());
-
-                 // Payout Logic
-                 for node in &mut nodes {
-                     if node.position.1 < 80.0 {
-                         node.wallet.balance_usdc += INSURANCE_PAYOUT;
-                     }
-                 }
-             }
+    narrate!("Live Nodes             | {:>14}", degrees.len());
+    narrate!("Links (one-way)        | {:>14}", format!("{} ({})", graph.link_count(), graph.one_way_link_count()));
+    if let (Some(min), Some(max)) = (degrees.first(), degrees.last()) {
+        let median = degrees[degrees.len() / 2];
+        narrate!("Degree min/med/max     | {:>14}", format!("{}/{}/{}", min, median, max));
+        narrate!("Avg Degree (out)       | {:>14.2}", graph.avg_degree());
+    }
+    narrate!("Connected Components   | {:>14}", components.len());
+    narrate!("Largest Components     | {:>14}", sizes.join(", "));
+    match graph.diameter() {
+        Some(hops) => narrate!("Diameter               | {:>14}", format!("{} hops", hops)),
+        None => narrate!("Diameter               | {:>14}", "no links"),
+    }
+    match graph.base_station_coverage(nodes) {
+        Some(share) => narrate!("BS Coverage (phones)   | {:>13.1}%", share * 100.0),
+        None => narrate!("BS Coverage (phones)   | {:>14}", "N/A"),
+    }
+    match graph.shortest_path(source, target) {
+        Some(path) => {
+            narrate!("Source -> Target       | {:>14}", format!("{} hops", path.len() - 1));
+            let path: Vec<String> = path.iter().map(u32::to_string).collect();
+            narrate!("  Path                 | {}", path.join(" -> "));
         }
+        None => narrate!("Source -> Target       | {:>14}", "UNREACHABLE"),
+    }
 
-        // 3. New Packet Generation
-        if nodes[start_node_id as usize].is_active {
-            packet_queue.push_back(Packet {
-                id: format!("M{}_{}", step, mode as i32),
-                history: vec![start_node_id],
-                target_id: target_node_id,
-                hops: 0,
-                ttl: 15,
-            });
+    // Degree distribution, one bar per degree
+    narrate!("\nDegree | Nodes");
+    for degree in 0..=degrees.last().copied().unwrap_or(0) {
+        let count = degrees.iter().filter(|&&d| d == degree).count();
+        if count > 0 {
+            narrate!("{:>6} | {:>3} {}", degree, count, "#".repeat(count));
         }
+    }
+}
 
-        // 4. Energy Drain (Idle)
-        for node in &mut nodes {
-            if node.is_active {
-                node.consume_battery(COST_IDLE);
-                total_energy_consumed += COST_IDLE;
+// Enter advances one step, a number advances that many, q quits
+fn interactive(config: &SimConfig, mode: SimMode) {
+    let mut sim = Simulation::new(mode, config);
+    let (source, target) = sim.endpoints();
+    narrate!("\n=== 🕹️  INTERACTIVE: {:?}, node {} -> {} ===", mode, source, target);
+    narrate!("Enter = 1 step, <n> = n steps, q = quit");
+    let mut delivered_total = 0;
+    let mut line = String::new();
+    while !sim.is_finished() {
+        line.clear();
+        if std::io::stdin().read_line(&mut line).unwrap_or(0) == 0 {
+            break; // EOF
+        }
+        let steps = match line.trim() {
+            "" => 1,
+            "q" | "quit" => break,
+            count => match count.parse::<u32>() {
+                Ok(n) => n,
+                Err(_) => {
+                    narrate!("❓ '{}': press Enter, type a step count, or q", count);
+                    continue;
+                }
+            },
+        };
+        for _ in 0..steps {
+            if sim.is_finished() {
+                break;
+            }
+            let report = sim.step();
+            delivered_total += report.delivered.len();
+            narrate!("step {:>3} | active {:>3} | in flight {:>4} | delivered {:>3} (total {})",
+                report.step, report.active_nodes, report.in_flight, report.delivered.len(), delivered_total);
+            for event in report.events.iter().filter_map(|e| e.log_tag()) {
+                narrate!("         | {}", event);
             }
         }
+    }
+    let stats = sim.stats();
+    narrate!("🏁 Stopped at step {}: {} of {} messages delivered", sim.state().step, stats.latencies.len(), stats.generated_packets);
+}
 
-        // 5. Packet Processing
-        let mut next_queue: VecDeque<Packet> = VecDeque::new();
-        let mut step_visited: HashMap<String, HashSet<u32>> = HashMap::new();
-        
-        // For visualization: track verified paths this step
-        let mut verified_packets: Vec<PacketLog> = Vec::new();
-
-        while let Some(packet) = packet_queue.pop_front() {
-            let current_node_id = *packet.history.last().unwrap();
-            
-            if current_node_id == target_node_id {
-                successful_packets += 1;
-                total_hops += packet.hops;
-                verified_packets.push(PacketLog { 
-                    id: packet.id.clone(), 
-                    path: packet.history.clone() 
-                });
-                continue;
-            }
-
-            if packet.ttl == 0 || !nodes[current_node_id as usize].is_active { continue; }
-
-            // TX Cost
-            nodes[current_node_id as usize].consume_battery(COST_TX);
-            total_energy_consumed += COST_TX;
-
-            let peers = nodes[current_node_id as usize].peers.clone();
-            
-            for neighbor_id in peers {
-                if packet.history.contains(&neighbor_id) { continue; } // No loops
-                
-                let visited_set = step_visited.entry(packet.id.clone()).or_insert(HashSet::new());
-                if visited_set.contains(&neighbor_id) { continue; } // No duplicate sends in same step
-
-                let neighbor = &nodes[neighbor_id as usize];
-                if !neighbor.is_active { continue; }
-
-                // --- ROUTING LOGIC ---
-                let should_forward = match mode {
-                    SimMode::Flooding => true, // Always forward (Dumb)
-                    SimMode::Swarm => {
-                        // Smart Logic
-                         if neighbor.node_type == NodeType::BaseStation {
-                             true
-                         } else {
-                             // Aggressive Unicorn Logic:
-                             // Only relay if battery is high AND random chance is low (sparse routing)
-                             let bat_p = neighbor.battery_level / BATTERY_FULL_SMARTPHONE;
-                             // e.g. 0.05 probability if full battery. 
-                             // This effectively makes Smartphones "last resort" or "sparse extensions"
-                             rng.random_bool(0.05 * (bat_p as f64)) 
-                         }
-                    }
-                };
+// One JSON object per line; with --metrics-stream the narration moves to stderr, so stdout is pure JSON lines
+fn print_metrics(mode: SimMode, report: &StepReport) {
+    println!("{}", serde_json::to_string(&StepMetrics::new(mode, report)).expect("metrics serialize"));
+}
 
-                if should_forward {
-                    nodes[neighbor_id as usize].consume_battery(COST_RX);
-                    total_energy_consumed += COST_RX;
-                    
-                    // Token Reward (Mining)
-                    if mode == SimMode::Swarm {
-                        nodes[neighbor_id as usize].wallet.balance_token += REWARD_RELAY;
-                    }
-
-                    let mut new_history = packet.history.clone();
-                    new_history.push(neighbor_id);
-                    
-                    next_queue.push_back(Packet {
-                        id: packet.id.clone(),
-                        history: new_history,
-                        target_id: packet.target_id,
-                        hops: packet.hops + 1,
-                        ttl: packet.ttl - 1,
-                    });
-                    
-                    visited_set.insert(neighbor_id);
+fn print_timings(timings: &[(SimMode, RunTimings)]) {
+    narrate!("\n=== ⏱️  Timing (ms) ===");
+    narrate!("{:<22} | {:>10} | {:>10} | {:>13}", "Mode", "Adjacency", "Stepping", "Serialization");
+    for (mode, t) in timings {
+        let ms = |d: std::time::Duration| d.as_secs_f64() * 1000.0;
+        narrate!("{:<22} | {:>10.2} | {:>10.2} | {:>13.2}",
+            mode_label(*mode), ms(t.adjacency), ms(t.stepping), ms(t.serialization));
+    }
+}
+
+// Convert an existing log to another format without re-simulating
+fn replay(input: &str, format: LogFormat, output: Option<&str>) -> Result<String, String> {
+    let input_format = LogFormat::from_path(input)
+        .ok_or_else(|| format!("can't tell the format of '{}' from its extension", input))?;
+    let output = output.map_or_else(|| format.default_path(), str::to_string);
+    if output == input {
+        return Err(format!("'{}' is already {:?}; pick another --log-format", input, format));
+    }
+    let bytes = std::fs::read(input).map_err(|e| format!("can't read '{}': {}", input, e))?;
+    let log_file = input_format.decode(&bytes)
+        .map_err(|e| format!("'{}' is not a schema v1+ log: {}", input, e))?;
+    format.write(&log_file, &output).map_err(|e| e.to_string())?;
+    Ok(output)
+}
+
+// Delivery ratio per TTL, and the smallest TTL that delivers everything
+fn ttl_sweep(config: &SimConfig, modes: &[SimMode], (min, max): (u32, u32)) {
+    if config.seed.is_none() {
+        narrate!("⚠️  No --seed: every TTL runs on a different topology");
+    }
+    // Every (TTL, mode) run is independent, so they go wide; collect keeps the order
+    let jobs: Vec<(u32, SimMode)> = (min..=max).flat_map(|ttl| modes.iter().map(move |&mode| (ttl, mode))).collect();
+    let ratios: Vec<f64> = jobs.par_iter()
+        .map(|&(ttl, mode)| or_exit(run_simulation(mode, None, &SimConfig { ttl, ..config.clone() }, None)).delivery_ratio())
+        .collect();
+    let rows: Vec<(u32, Vec<f64>)> = (min..=max)
+        .zip(ratios.chunks(modes.len()))
+        .map(|(ttl, chunk)| (ttl, chunk.to_vec()))
+        .collect();
+
+    narrate!("\n=== ⏳ TTL SWEEP ===");
+    let header: Vec<String> = modes.iter().map(|mode| format!("{:>15}", format!("{:?}", mode))).collect();
+    narrate!("TTL  | {}", header.join(" | "));
+    for (ttl, ratios) in &rows {
+        let cells: Vec<String> = ratios.iter().map(|r| format!("{:>14.1}%", r * 100.0)).collect();
+        narrate!("{:<4} | {}", ttl, cells.join(" | "));
+    }
+    for (i, mode) in modes.iter().enumerate() {
+        match rows.iter().find(|(_, ratios)| ratios[i] >= 1.0) {
+            Some((ttl, _)) => narrate!("{:?}: full delivery from TTL {}", mode, ttl),
+            None => narrate!("{:?}: no TTL in {}..={} delivers everything", mode, min, max),
+        }
+    }
+}
+
+// "How many base stations for 95% delivery?": mean delivery and energy over
+// `seeds` seeds per ratio, stopping at the first ratio where every mode hits `target`
+fn bs_sweep(config: &SimConfig, modes: &[SimMode], (start, step, end): (f64, f64, f64), seeds: u64, target: f64) {
+    let base_seed = config.seed.unwrap_or_else(|| {
+        narrate!("⚠️  No --seed: sweeping from seed 0");
+        0
+    });
+    // Stepping by index keeps 0.1 + 0.1 + 0.1 from drifting past `end`
+    let ratios: Vec<f64> = (0..=((end - start) / step + 1e-9) as u32).map(|i| start + i as f64 * step).collect();
+
+    narrate!("\n=== 🗼 BASE-STATION SWEEP ({} seeds per ratio, target {:.0}%) ===", seeds, target * 100.0);
+    let header: Vec<String> = modes.iter().map(|&mode| format!("{:>24}", mode_label(mode))).collect();
+    narrate!("BS ratio | {}", header.join(" | "));
+    let mut reached: Vec<Option<f64>> = vec![None; modes.len()];
+    for bs_ratio in ratios {
+        // One ratio's (mode, seed) runs go wide; the next ratio waits to see if we're done
+        let jobs: Vec<(SimMode, u64)> = modes.iter().flat_map(|&mode| (0..seeds).map(move |i| (mode, i))).collect();
+        let runs: Vec<SimStats> = jobs.par_iter()
+            .map(|&(mode, i)| {
+                let config = SimConfig { bs_ratio, seed: Some(base_seed.wrapping_add(i)), ..config.clone() };
+                or_exit(run_simulation(mode, None, &config, None))
+            })
+            .collect();
+        let cells: Vec<String> = runs.chunks(seeds as usize).zip(&mut reached)
+            .map(|(stats, reached)| {
+                let delivery = stats.iter().map(SimStats::delivery_ratio).sum::<f64>() / seeds as f64;
+                let energy = stats.iter().map(|s| s.total_energy as f64).sum::<f64>() / seeds as f64;
+                if delivery >= target && reached.is_none() {
+                    *reached = Some(bs_ratio);
                 }
-            }
+                format!("{:>6.1}% {:>10.0} J", delivery * 100.0, energy)
+            })
+            .map(|cell| format!("{:>24}", cell))
+            .collect();
+        narrate!("{:<8.2} | {}", bs_ratio, cells.join(" | "));
+        if reached.iter().all(Option::is_some) {
+            break;
+        }
+    }
+    for (mode, reached) in modes.iter().zip(&reached) {
+        match reached {
+            Some(bs_ratio) => narrate!("{}: {:.0}% delivery from a base-station ratio of {:.2}", mode_label(*mode), target * 100.0, bs_ratio),
+            None => narrate!("{}: no ratio in {:.2}..={:.2} reaches {:.0}% delivery", mode_label(*mode), start, end, target * 100.0),
+        }
+    }
+}
+
+// `count` runs per mode in parallel. Run i uses seed + i whatever thread picks it
+// up, so the results match a serial loop. Nothing is exported.
+fn repeat_runs(config: &SimConfig, modes: &[SimMode], count: u64) {
+    let base_seed = config.seed.unwrap_or_else(|| {
+        narrate!("⚠️  No --seed: repeating from seed 0");
+        0
+    });
+    let jobs: Vec<(SimMode, u64)> = modes.iter().flat_map(|&mode| (0..count).map(move |i| (mode, i))).collect();
+    let runs: Vec<SimStats> = jobs.par_iter()
+        .map(|&(mode, i)| {
+            let config = SimConfig { seed: Some(base_seed.wrapping_add(i)), ..config.clone() };
+            or_exit(run_simulation(mode, None, &config, None))
+        })
+        .collect();
+
+    let mean_std = |values: &[f64]| {
+        let mean = values.iter().sum::<f64>() / values.len() as f64;
+        let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / values.len() as f64;
+        (mean, variance.sqrt())
+    };
+    narrate!("\n=== 🔁 {} RUNS PER MODE (seeds {}..{}) ===", count, base_seed, base_seed.wrapping_add(count - 1));
+    // Each seed is its own topology (and pair, with --target random), so Jain's
+    // index over the runs shows whether a mode serves some flows far better than others
+    narrate!("{:<16} | {:>17} | {:>19} | {:>14} | {:>8}", "Mode", "Delivery ratio", "Energy", "Avg latency", "Fairness");
+    for (mode, stats) in modes.iter().zip(runs.chunks(count as usize)) {
+        let ratios: Vec<f64> = stats.iter().map(SimStats::delivery_ratio).collect();
+        let (ratio, ratio_std) = mean_std(&ratios);
+        let (energy, energy_std) = mean_std(&stats.iter().map(|s| s.total_energy as f64).collect::<Vec<_>>());
+        let latencies: Vec<f64> = stats.iter().filter_map(SimStats::avg_latency).collect();
+        let latency = if latencies.is_empty() { "N/A".to_string() } else { format!("{:.2}", mean_std(&latencies).0) };
+        let fairness = jain_fairness(&ratios).map_or("N/A".to_string(), |index| format!("{:.3}", index));
+        narrate!("{:<16} | {:>7.1}% ± {:>5.1}% | {:>9.0} ± {:>7.0} | {:>14} | {:>8}",
+            mode_label(*mode), ratio * 100.0, ratio_std * 100.0, energy, energy_std, latency, fairness);
+    }
+
+    // Runs without a delivery have no latency, so its sample can be smaller
+    narrate!("\n95% confidence intervals of the mean (Student's t, n = samples)");
+    narrate!("{:<16} | {:>21} | {:>25} | {:>21}", "Mode", "Delivery ratio", "Energy", "Avg latency");
+    let fmt_ci = |values: &[f64], scale: f64, precision: usize, unit: &str| match confidence_interval_95(values) {
+        Some((mean, half)) => format!("{:.*}{} ± {:.*}{} (n={})",
+            precision, mean * scale, unit, precision, half * scale, unit, values.len()),
+        None => format!("N/A (n={})", values.len()),
+    };
+    for (mode, stats) in modes.iter().zip(runs.chunks(count as usize)) {
+        let ratios: Vec<f64> = stats.iter().map(SimStats::delivery_ratio).collect();
+        let energies: Vec<f64> = stats.iter().map(|s| s.total_energy as f64).collect();
+        let latencies: Vec<f64> = stats.iter().filter_map(SimStats::avg_latency).collect();
+        narrate!("{:<16} | {:>21} | {:>25} | {:>21}", mode_label(*mode),
+            fmt_ci(&ratios, 100.0, 1, "%"), fmt_ci(&energies, 1.0, 0, ""), fmt_ci(&latencies, 1.0, 2, ""));
+    }
+}
+
+// How each mode holds up as the mesh grows. The grid grows with the node count so
+// density (and so neighbors per node) stays put; only the mesh gets wider. Runs go
+// one at a time so the wall-clock column isn't skewed by runs competing for cores.
+fn scale_test(config: &SimConfig, modes: &[SimMode], counts: &[u32], format: TableFormat) {
+    let mut table = Table {
+        corner: "Nodes".to_string(),
+        header: ["Grid", "Mode", "Delivery %", "Energy / Deliv", "Wall ms"].map(str::to_string).to_vec(),
+        rows: Vec::new(),
+    };
+    for &node_count in counts {
+        let grid_size = config.grid_size * (node_count as f64 / config.node_count as f64).sqrt();
+        let config = SimConfig { node_count, grid_size, ..config.clone() };
+        if let Err(err) = config.validate() {
+            narrate!("⚠️  Skipping {} nodes: {}", node_count, err);
+            continue;
         }
-        packet_queue = next_queue;
-        
-        // SAVE LOGS (Only for Swarm mode usually, or we can save both. Let's save Swarm for v4 visualization)
-        if export_logs {
-             let node_logs = nodes.iter().map(|n| NodeLog {
-                 id: n.id,
-                 lat: n.lat,
-                 lon: n.lon,
-                 is_active: n.is_active,
-                 node_type: format!("{:?}", n.node_type),
-                 battery: n.battery_level,
-             }).collect();
-             
-             sim_logs.push(SimLog {
-                 step,
-                 nodes: node_logs,
-                 packets: verified_packets,
-                 events: current_step_events,
-             });
+        for &mode in modes {
+            let start = std::time::Instant::now();
+            let stats = or_exit(run_simulation(mode, None, &config, None));
+            let wall = start.elapsed();
+            let delivered = stats.latencies.len();
+            table.rows.push((node_count.to_string(), vec![
+                format!("{:.0}", grid_size),
+                mode_label(mode),
+                format!("{:.1}", stats.delivery_ratio() * 100.0),
+                if delivered > 0 { format!("{:.1}", stats.total_energy as f64 / delivered as f64) } else { "N/A".to_string() },
+                format!("{:.1}", wall.as_secs_f64() * 1000.0),
+            ]));
         }
     }
+    narrate!("\n=== 📈 SCALE TEST ({} modes, density of {} nodes on {:.0}x{:.0}) ===",
+        modes.len(), config.node_count, config.grid_size, config.grid_size);
+    narrate!("{}", table.render(format));
+}
+
+// Same scenario, slightly different geometry each time: a result that swings
+// far from the exact layout hinged on a lucky (or unlucky) placement
+fn jitter_runs(config: &SimConfig, modes: &[SimMode], count: u64) {
+    if config.position_jitter_stddev == 0.0 {
+        narrate!("⚠️  --jitter-runs without --jitter: every run has the exact layout");
+    }
+    let jobs: Vec<(SimMode, Option<u64>)> = modes.iter()
+        .flat_map(|&mode| std::iter::once(None).chain((0..count).map(Some)).map(move |jitter| (mode, jitter)))
+        .collect();
+    let runs: Vec<SimStats> = jobs.par_iter()
+        .map(|&(mode, jitter)| {
+            let config = match jitter {
+                Some(jitter_seed) => SimConfig { jitter_seed, ..config.clone() },
+                None => SimConfig { position_jitter_stddev: 0.0, ..config.clone() },
+            };
+            or_exit(run_simulation(mode, None, &config, None))
+        })
+        .collect();
 
-    if export_logs {
-        let json_data = serde_json::to_string_pretty(&sim_logs).unwrap();
-        let mut file = File::create("simulation_log.json").unwrap();
-        file.write_all(json_data.as_bytes()).unwrap();
-        println!("💾 Log exported to 'simulation_log.json'");
+    narrate!("\n=== 〰️  {} JITTERED LAYOUTS PER MODE (σ = {}) ===", count, config.position_jitter_stddev);
+    narrate!("{:<16} | {:>9} | {:>17} | {:>15} | {:>10}", "Mode", "Exact", "Jittered", "Range", "Max swing");
+    for (mode, stats) in modes.iter().zip(runs.chunks(count as usize + 1)) {
+        let exact = stats[0].delivery_ratio();
+        let ratios: Vec<f64> = stats[1..].iter().map(SimStats::delivery_ratio).collect();
+        let mean = ratios.iter().sum::<f64>() / ratios.len() as f64;
+        let std = (ratios.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / ratios.len() as f64).sqrt();
+        let (min, max) = ratios.iter().fold((f64::MAX, f64::MIN), |(lo, hi), &r| (lo.min(r), hi.max(r)));
+        let swing = ratios.iter().map(|r| (r - exact).abs()).fold(0.0, f64::max);
+        narrate!("{:<16} | {:>8.1}% | {:>7.1}% ± {:>5.1}% | {:>5.1}%..{:>5.1}% | {:>9.1}%",
+            mode_label(*mode), exact * 100.0, mean * 100.0, std * 100.0, min * 100.0, max * 100.0, swing * 100.0);
     }
+}
+
+fn export_results(cli: &Cli, runs: &[(SimMode, SimStats)]) {
+    if let Some(path) = &cli.results {
+        or_exit(BenchmarkResult::new(&cli.config, runs).write(path));
+        narrate!("📝 Results written to '{}'", path);
+    }
+}
 
-    SimStats {
-        total_energy: total_energy_consumed,
-        success_packets: successful_packets,
-        total_hops: total_hops,
+// Wallets come from the same run as the step log
+#[cfg(feature = "tokenomics")]
+fn export_wallets(cli: &Cli, stats: &SimStats) {
+    if let Some(path) = &cli.wallets_out {
+        or_exit(write_wallets(&stats.wallets, path));
+        narrate!("💰 Wallets exported to '{}'", path);
     }
 }
 
+// Export failures are fatal but shouldn't panic
+fn or_exit<T>(result: Result<T, ExportError>) -> T {
+    result.unwrap_or_else(|err| {
+        eprintln!("❌ Log export failed: {}", err);
+        std::process::exit(1);
+    })
+}
+
 fn main() {
-    println!("=== 🦄 ResilientMesh v4.0 Unicorn Benchmark ===");
-    
-    // 1. Run Flooding (Baseline)
-    let stats_flood = run_simulation(SimMode::Flooding, false);
-    
-    // 2. Run Swarm (New Tech) - Export logs for this one
-    let stats_swarm = run_simulation(SimMode::Swarm, true);
+    let cli = match parse_args() {
+        Ok(cli) => cli,
+        Err(msg) => {
+            eprintln!("❌ {}", msg);
+            std::process::exit(2);
+        }
+    };
+    if cli.metrics_stream {
+        narrate_to_stderr(true);
+    }
 
-    println!("\n=== 📊 BENCHMARK RESULTS ===");
-    println!("Metric                 | Flooding (Old) | Swarm (Unicorn) | Improvement");
-    println!("-----------------------|----------------|-----------------|------------");
-    
-    let energy_imp = (stats_flood.total_energy - stats_swarm.total_energy) / stats_flood.total_energy * 100.0;
-    println!("Total Energy Consumed  | {:>14.1} | {:>15.1} | {:>10.1}% 🚀", 
-        stats_flood.total_energy, stats_swarm.total_energy, energy_imp);
-
-    println!("Packets Delivered      | {:>14} | {:>15} |", 
-        stats_flood.success_packets, stats_swarm.success_packets);
-
-    println!("Total Hops (Traffic)   | {:>14} | {:>15} |", 
-        stats_flood.total_hops, stats_swarm.total_hops);
-        
-    let battery_extension = stats_flood.total_energy / stats_swarm.total_energy;
-    println!("Battery Life Extension |         1.0x |           {:>.1}x | 🔋", battery_extension);
+    if let Some(input) = &cli.replay {
+        match replay(input, cli.log_format, cli.output.as_deref()) {
+            Ok(output) => narrate!("💾 Replayed '{}' into '{}'", input, output),
+            Err(msg) => {
+                eprintln!("❌ {}", msg);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    let config = &cli.config;
+    if let Err(err) = config.validate() {
+        eprintln!("❌ Invalid configuration: {}", err);
+        std::process::exit(1);
+    }
+    narrate!("=== 🦄 ResilientMesh v4.0 Unicorn Benchmark ===");
+
+    if cli.dry_run {
+        dry_run(config);
+        return;
+    }
+    if cli.analyze_only {
+        analyze(config);
+        return;
+    }
+    if cli.interactive {
+        interactive(config, cli.mode.unwrap_or(SimMode::Swarm));
+        return;
+    }
+
+    let sweep_modes = match cli.mode {
+        Some(mode) => vec![mode],
+        None => cli.modes.clone().unwrap_or(vec![SimMode::Flooding, SimMode::Swarm]),
+    };
+    if let Some(range) = cli.ttl_sweep {
+        ttl_sweep(config, &sweep_modes, range);
+        return;
+    }
+    if let Some(range) = cli.bs_sweep {
+        bs_sweep(config, &sweep_modes, range, cli.repeat.unwrap_or(BS_SWEEP_SEEDS), cli.sweep_target);
+        return;
+    }
+    if let Some(count) = cli.repeat {
+        repeat_runs(config, &sweep_modes, count);
+        return;
+    }
+    if let Some(count) = cli.jitter_runs {
+        jitter_runs(config, &sweep_modes, count);
+        return;
+    }
+    if let Some(counts) = &cli.scale_test {
+        scale_test(config, &sweep_modes, counts, cli.table_format);
+        return;
+    }
+
+    if let Some(mode) = cli.mode {
+        let mut stream = |report: &StepReport| print_metrics(mode, report);
+        let (stats, timings) = or_exit(run_simulation_timed(mode, Some(&cli.export()), config, None,
+            cli.metrics_stream.then_some(&mut stream as _)));
+        #[cfg(feature = "tokenomics")]
+        export_wallets(&cli, &stats);
+        narrate!("\n=== 📊 RESULTS: {:?} ===", mode);
+        if cli.timing {
+            print_timings(&[(mode, timings)]);
+        }
+        print_stats(config, mode, &stats);
+        let runs = [(mode, stats)];
+        export_results(&cli, &runs);
+        print_hotspots(&runs, cli.hotspots);
+        print_drop_reasons(&runs);
+        if let Some(window) = cli.rate_window {
+            print_delivery_rate(&runs, window);
+        }
+        return;
+    }
     
-    println!("\n[Next Steps]");
-    println!("1. Open 'map.html' (generate it with python src/visualize.py)");
-    println!("2. See the insurance payout event in the log.");
-}
\ No newline at end of file
+    let mut modes = cli.modes.clone().unwrap_or(vec![SimMode::Flooding, SimMode::Swarm]);
+    if let Some(k) = cli.weighted_split
+        && !modes.contains(&SimMode::WeightedSplit { k })
+    {
+        modes.push(SimMode::WeightedSplit { k });
+    }
+    if cli.fair_swarm && !modes.contains(&SimMode::FairSwarm) {
+        modes.push(SimMode::FairSwarm);
+    }
+    if !modes.contains(&cli.baseline) {
+        modes.insert(0, cli.baseline);
+    }
+
+    // Export logs for Swarm (New Tech) only
+    let mut runs: Vec<(SimMode, SimStats)> = Vec::new();
+    let mut timings: Vec<(SimMode, RunTimings)> = Vec::new();
+    for mode in modes {
+        let export = (mode == SimMode::Swarm).then(|| cli.export());
+        let mut stream = |report: &StepReport| print_metrics(mode, report);
+        let (stats, run_timings) = or_exit(run_simulation_timed(mode, export.as_ref(), config, None,
+            cli.metrics_stream.then_some(&mut stream as _)));
+        #[cfg(feature = "tokenomics")]
+        if export.is_some() {
+            export_wallets(&cli, &stats);
+        }
+        runs.push((mode, stats));
+        timings.push((mode, run_timings));
+    }
+    if cli.timing {
+        print_timings(&timings);
+    }
+
+    narrate!("\n=== 📊 BENCHMARK RESULTS (baseline: {:?}) ===", cli.baseline);
+    print_comparison(config, &runs, Some(cli.baseline), cli.table_format);
+    export_results(&cli, &runs);
+    print_hotspots(&runs, cli.hotspots);
+    print_drop_reasons(&runs);
+    if let Some(window) = cli.rate_window {
+        print_delivery_rate(&runs, window);
+    }
+
+    // How much of flooding's cost was pure re-replication
+    if config.flood_suppression
+        && let Some((_, suppressed)) = runs.iter().find(|(mode, _)| *mode == SimMode::Flooding)
+    {
+        let unsuppressed = SimConfig { flood_suppression: false, ..config.clone() };
+        let raw = or_exit(run_simulation(SimMode::Flooding, None, &unsuppressed, None));
+        let saved = suppressed.energy_saved_vs(&raw).map_or("N/A".to_string(), |saved| format!("{:.1}%", saved));
+        narrate!("\n🧹 Flood suppression: {:.1} energy vs {:.1} unsuppressed ({} saved), {} vs {} delivered",
+            suppressed.total_energy, raw.total_energy, saved,
+            suppressed.success_packets, raw.success_packets);
+    }
+
+    narrate!("\n[Next Steps]");
+    narrate!("1. Open 'map.html' (generate it with python src/visualize.py)");
+    #[cfg(feature = "tokenomics")]
+    narrate!("2. See the insurance payout event in the log.");
+}
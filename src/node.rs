@@ -0,0 +1,389 @@
+use serde::{Serialize, Deserialize};
+use rand::Rng;
+
+use std::cell::Cell;
+
+use crate::config::{BsPlacement, DistanceMetric, Placement, SimConfig, BATTERY_BASE_STATION};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum NodeType {
+    Smartphone,
+    BaseStation,
+}
+
+// What a node does with other people's traffic, independent of its hardware
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum NodeRole {
+    Gateway,  // Base stations: infrastructure uplink, always relays
+    Peer,     // Ordinary phone: relays subject to routing and cooperation
+    Relay,    // Dedicated relay firmware: always forwards
+    Endpoint, // Only sources or sinks traffic, never forwards
+}
+
+impl NodeRole {
+    // Relays never free-ride
+    pub fn always_forwards(&self) -> bool {
+        matches!(self, NodeRole::Gateway | NodeRole::Relay)
+    }
+}
+
+#[cfg(feature = "tokenomics")]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Wallet {
+    pub address: String,
+    pub balance_token: f32,
+    pub balance_usdc: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Node {
+    pub id: u32,
+    // (x, y) relative coordinates (0-grid_size, 200 by default)
+    pub position: (f64, f64),
+    // Lat/Lon for visualization (calculated from position)
+    pub lat: f64,
+    pub lon: f64,
+    pub is_active: bool,
+    pub peers: Vec<u32>,
+    pub node_type: NodeType,
+    pub role: NodeRole,
+    pub battery_level: f32,
+    pub battery_capacity: f32, // This device's full charge
+    pub harvest_rate: f32, // Energy regained per step (solar), capped at capacity
+    pub activation_step: i32, // Offline until this step (responder-deployed base stations); <= 1 = from the start
+    pub transmission_range: f64,
+    pub cooperation_prob: f64, // Chance of relaying a packet it holds; selfish phones save battery
+    pub packets_relayed: u32, // Copies forwarded on behalf of others
+    #[cfg(feature = "tokenomics")]
+    pub wallet: Wallet,
+}
+
+#[derive(Debug, Clone)]
+pub struct Packet {
+    pub id: String,
+    pub history: Vec<u32>,
+    pub target_id: u32,
+    pub hops: u32,
+    pub ttl: u32,
+    pub size_bytes: u32, // Scales TX/RX energy
+    pub created_step: i32, // For latency: delivery step - created step
+    // Dead reckoning: where the target was last seen, and when. Geographic
+    // routing steers toward this rather than the target's true position.
+    pub target_hint: (f64, f64),
+    pub hint_step: i32,
+    // Store-carry-forward: since when the current holder has been sitting on this
+    // copy, and whether any holder along its path ever did
+    pub held_since: Option<i32>,
+    pub carried: bool,
+    pub hop_steps: Vec<i32>, // Step each `history` entry took hold of this copy (same length as history)
+}
+
+impl Packet {
+    // 1.0 when created, times `decay` per step since; re-sends keep the original creation step
+    pub fn importance(&self, step: i32, decay: f64) -> f64 {
+        decay.powi(step - self.created_step)
+    }
+}
+
+// What a packet id encodes: "M<source>.<seq>", or "F<flow>.M<source>.<seq>" when the
+// traffic belongs to a named flow. Sequence numbers count up per run, so ids never collide
+// no matter how many messages a step generates; copies of one message share its id.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct PacketId {
+    pub flow: Option<u32>,
+    pub source: u32,
+    pub seq: u64,
+}
+
+impl PacketId {
+    pub fn parse(id: &str) -> Option<PacketId> {
+        let (flow, rest) = match id.strip_prefix('F') {
+            Some(tagged) => {
+                let (flow, rest) = tagged.split_once('.')?;
+                (Some(flow.parse().ok()?), rest)
+            }
+            None => (None, id),
+        };
+        let (source, seq) = rest.strip_prefix('M')?.split_once('.')?;
+        Some(PacketId { flow, source: source.parse().ok()?, seq: seq.parse().ok()? })
+    }
+}
+
+impl std::fmt::Display for PacketId {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        if let Some(flow) = self.flow {
+            write!(f, "F{}.", flow)?;
+        }
+        write!(f, "M{}.{}", self.source, self.seq)
+    }
+}
+
+// SplitMix64 finalizer: a stable, platform-independent mix (unlike std's hasher)
+fn mix64(mut z: u64) -> u64 {
+    z = z.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+// Seed for a node's own decision stream: independent of every other node's
+pub fn node_seed(master_seed: u64, id: u32) -> u64 {
+    mix64(mix64(master_seed) ^ id as u64)
+}
+
+// Duty cycling: whether a node's radio is on this step. A stable hash of
+// (seed, id, step), so the schedule doesn't shift with anything else's randomness.
+pub fn radio_on(master_seed: u64, id: u32, step: i32, duty_cycle: f64) -> bool {
+    if duty_cycle >= 1.0 {
+        return true;
+    }
+    let draw = (mix64(node_seed(master_seed, id) ^ step as u64) >> 11) as f64 / (1u64 << 53) as f64;
+    draw < duty_cycle
+}
+
+// Gaussian nudge (Box-Muller) for a node's position, hashed from (jitter seed, id)
+// so perturbing the geometry never shifts the main stream's placement draws
+pub fn position_jitter(jitter_seed: u64, id: u32, stddev: f64) -> (f64, f64) {
+    let uniform = |salt: u64| (mix64(node_seed(jitter_seed, id) ^ salt) >> 11) as f64 / (1u64 << 53) as f64;
+    let radius = (-2.0 * (1.0 - uniform(1)).ln()).sqrt() * stddev; // 1 - u keeps ln away from 0
+    let angle = std::f64::consts::TAU * uniform(2);
+    (radius * angle.cos(), radius * angle.sin())
+}
+
+// Same seed + id => same address, so runs can be diffed by wallet
+#[cfg(feature = "tokenomics")]
+pub fn wallet_address(seed: u64, id: u32) -> String {
+    let hash = mix64(seed ^ mix64(id as u64));
+    format!("0x{:04x}...{:04x}", hash & 0xffff, id)
+}
+
+// Per-topology placement state: cluster centres are drawn once, before any node
+pub struct Layout {
+    centers: Vec<(f64, f64)>,
+    next_site: Cell<usize>, // Explicit base-station sites handed out so far
+}
+
+impl Layout {
+    pub fn new(config: &SimConfig, rng: &mut impl Rng) -> Self {
+        let centers = match config.placement {
+            Placement::Clustered { clusters, .. } => (0..clusters)
+                .map(|_| (rng.random_range(0.0..config.grid_size), rng.random_range(0.0..config.grid_size)))
+                .collect(),
+            _ => Vec::new(),
+        };
+        Layout { centers, next_site: Cell::new(0) }
+    }
+
+    fn position(&self, id: u32, node_type: &NodeType, config: &SimConfig, rng: &mut impl Rng) -> (f64, f64) {
+        let size = config.grid_size;
+        if *node_type == NodeType::BaseStation {
+            match &config.bs_placement {
+                BsPlacement::SameAsPhones => {}
+                BsPlacement::Uniform => return (rng.random_range(0.0..size), rng.random_range(0.0..size)),
+                BsPlacement::EdgeBiased => {
+                    // Squaring the depth piles stations up against a random edge
+                    let along = rng.random_range(0.0..size);
+                    let depth = rng.random::<f64>().powi(2) * size / 2.0;
+                    return match rng.random_range(0..4) {
+                        0 => (along, depth),
+                        1 => (along, size - depth),
+                        2 => (depth, along),
+                        _ => (size - depth, along),
+                    };
+                }
+                BsPlacement::Sites(sites) => {
+                    let next = self.next_site.get();
+                    if let Some(&site) = sites.get(next) {
+                        self.next_site.set(next + 1);
+                        return site;
+                    }
+                }
+            }
+        }
+        match config.placement {
+            Placement::Uniform => (rng.random_range(0.0..size), rng.random_range(0.0..size)),
+            Placement::Grid => {
+                let side = (config.node_count as f64).sqrt().ceil() as u32;
+                let spacing = size / side as f64;
+                let (row, col) = (id / side, id % side);
+                ((col as f64 + 0.5) * spacing, (row as f64 + 0.5) * spacing)
+            }
+            Placement::Clustered { spread, .. } => {
+                let center = self.centers[(id as usize) % self.centers.len()];
+                // Base stations sit in the middle of their village
+                if *node_type == NodeType::BaseStation || spread == 0.0 {
+                    return center;
+                }
+                let x = center.0 + rng.random_range(-spread..=spread);
+                let y = center.1 + rng.random_range(-spread..=spread);
+                (x.clamp(0.0, size), y.clamp(0.0, size))
+            }
+        }
+    }
+}
+
+impl Node {
+    pub fn new(id: u32, config: &SimConfig, layout: &Layout, rng: &mut impl Rng) -> Self {
+        // bs_ratio (15% by default) BaseStation
+        // Base stations are operator infrastructure and always relay
+        let (node_type, battery, harvest_rate, range, cooperation_prob) = if rng.random_bool(config.bs_ratio) {
+            (NodeType::BaseStation, BATTERY_BASE_STATION, config.bs_harvest_rate, 180.0, 1.0)
+        } else {
+            let (min, max) = config.battery_range;
+            let battery = rng.random_range(min..=max);
+            (NodeType::Smartphone, battery, config.phone_harvest_rate, 40.0, config.cooperation_prob)
+        };
+
+        let (mut x, mut y) = layout.position(id, &node_type, config, rng);
+        if config.position_jitter_stddev > 0.0 {
+            let (dx, dy) = position_jitter(config.jitter_seed, id, config.position_jitter_stddev);
+            x = (x + dx).clamp(0.0, config.grid_size);
+            y = (y + dy).clamp(0.0, config.grid_size);
+        }
+        let (relay_ratio, endpoint_ratio) = config.role_ratios;
+        let role = if node_type == NodeType::BaseStation {
+            NodeRole::Gateway
+        } else if relay_ratio + endpoint_ratio > 0.0 {
+            let draw: f64 = rng.random();
+            if draw < relay_ratio {
+                NodeRole::Relay
+            } else if draw < relay_ratio + endpoint_ratio {
+                NodeRole::Endpoint
+            } else {
+                NodeRole::Peer
+            }
+        } else {
+            NodeRole::Peer
+        };
+        let activation_step = match config.bs_activation_range {
+            Some((first, last)) if node_type == NodeType::BaseStation => rng.random_range(first..=last),
+            _ => 0,
+        };
+
+        // Nice, France unless the config says otherwise
+        let (lat, lon) = config.projection.to_lat_lon((x, y));
+
+        Node {
+            id,
+            position: (x, y),
+            lat,
+            lon,
+            is_active: true,
+            peers: Vec::new(),
+            node_type,
+            role,
+            battery_level: battery,
+            battery_capacity: battery,
+            harvest_rate,
+            activation_step,
+            transmission_range: range,
+            cooperation_prob,
+            packets_relayed: 0,
+            #[cfg(feature = "tokenomics")]
+            wallet: Wallet {
+                address: wallet_address(config.seed.unwrap_or(0), id),
+                balance_token: 0.0,
+                balance_usdc: 0.0,
+            },
+        }
+    }
+
+    pub fn distance_to(&self, other: &Node, metric: DistanceMetric) -> f64 {
+        self.distance_to_point(other.position, metric)
+    }
+
+    pub fn distance_to_point(&self, point: (f64, f64), metric: DistanceMetric) -> f64 {
+        let dx = self.position.0 - point.0;
+        let dy = self.position.1 - point.1;
+        match metric {
+            DistanceMetric::Euclidean => (dx * dx + dy * dy).sqrt(),
+            DistanceMetric::Manhattan => dx.abs() + dy.abs(),
+        }
+    }
+
+    pub fn consume_battery(&mut self, cost: f32) {
+        self.battery_level = (self.battery_level - cost).max(0.0);
+        if self.battery_level <= 0.0 {
+            self.is_active = false;
+        }
+    }
+
+    // Solar top-up; a node that already died stays down
+    pub fn harvest(&mut self) {
+        if self.is_active {
+            self.battery_level = (self.battery_level + self.harvest_rate).min(self.battery_capacity);
+        }
+    }
+}
+
+// Test fixture, shared by the unit and integration tests: hand-placed nodes with ids in
+// order and a 40-unit range. `types` gives each node's type (None = all smartphones);
+// everything else comes from Node::new.
+#[doc(hidden)]
+pub fn placed_nodes(config: &SimConfig, positions: &[(f64, f64)], types: Option<&[NodeType]>) -> Vec<Node> {
+    use rand::SeedableRng;
+    let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+    let layout = Layout::new(config, &mut rng);
+    positions.iter().enumerate().map(|(id, &position)| {
+        let mut node = Node::new(id as u32, config, &layout, &mut rng);
+        node.node_type = types.map_or(NodeType::Smartphone, |types| types[id].clone());
+        node.transmission_range = 40.0;
+        node.position = position;
+        node
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{COST_IDLE, COST_RX, COST_TX};
+
+    fn node_with(node_type: NodeType, battery: f32) -> Node {
+        let mut node = placed_nodes(&SimConfig::default(), &[(100.0, 100.0)], Some(&[node_type])).remove(0);
+        node.battery_level = battery;
+        node.battery_capacity = battery;
+        node
+    }
+
+    #[test]
+    fn partial_drain_keeps_node_active() {
+        let mut node = node_with(NodeType::Smartphone, 10.0);
+        node.consume_battery(COST_TX);
+        assert_eq!(node.battery_level, 5.0);
+        assert!(node.is_active);
+    }
+
+    #[test]
+    fn draining_to_exactly_zero_deactivates() {
+        let mut node = node_with(NodeType::Smartphone, COST_TX);
+        node.consume_battery(COST_TX);
+        assert_eq!(node.battery_level, 0.0);
+        assert!(!node.is_active);
+    }
+
+    #[test]
+    fn overshoot_clamps_at_zero() {
+        // One step's idle + TX + RX is more than what's left
+        let mut node = node_with(NodeType::Smartphone, 3.0);
+        for cost in [COST_IDLE, COST_TX, COST_RX] {
+            node.consume_battery(cost);
+            assert!(node.battery_level >= 0.0);
+        }
+        assert_eq!(node.battery_level, 0.0);
+        assert!(!node.is_active);
+    }
+
+    #[test]
+    fn base_station_outlasts_a_busy_run_on_default_solar() {
+        // Base stations are no longer infinite, but by default the panel covers
+        // idle drain plus a send and a receive every step, so they never go down
+        let mut node = node_with(NodeType::BaseStation, BATTERY_BASE_STATION);
+        node.harvest_rate = SimConfig::default().bs_harvest_rate;
+        for _ in 0..10_000 {
+            node.consume_battery(COST_IDLE + COST_TX + COST_RX);
+            node.harvest();
+        }
+        assert!(node.is_active);
+        assert_eq!(node.battery_level, BATTERY_BASE_STATION);
+    }
+}
@@ -0,0 +1,165 @@
+use rand::Rng;
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::config::{SimConfig, SimMode, ADAPTIVE_EXPLORE, ADAPTIVE_FANOUT, ADAPTIVE_INITIAL_SCORE, SWARM_RELAY_PROB};
+use crate::node::{Node, NodeRole, NodeType, Packet};
+use crate::sim::{weighted_sample, SimRng};
+
+// Read-only view of the mesh a strategy may consult
+pub struct RoutingContext<'a> {
+    pub step: i32,
+    pub config: &'a SimConfig,
+    pub nodes: &'a [Node],
+    pub link_scores: &'a BTreeMap<(u32, u32), f64>, // Adaptive's learned (from, to) reliabilities
+    pub relay_load: &'a [f64], // Decayed count of each node's recent relays, indexed by node id
+}
+
+// How a holder picks which neighbors get a copy. `select_neighbors` runs once
+// per held packet and may narrow the field up front; `should_forward` then runs
+// for every remaining neighbor, in peer order. `rng` is the holder's own stream.
+pub trait RoutingStrategy {
+    // None = no pre-selection, ask `should_forward` about every neighbor
+    fn select_neighbors(
+        &self,
+        _ctx: &RoutingContext,
+        _current: &Node,
+        _candidates: &[u32],
+        _packet: &Packet,
+        _rng: &mut SimRng,
+    ) -> Option<BTreeSet<u32>> {
+        None
+    }
+
+    fn should_forward(&self, current: &Node, neighbor: &Node, packet: &Packet, rng: &mut SimRng) -> bool;
+}
+
+impl SimMode {
+    // The built-in strategy behind each mode
+    pub fn strategy(&self) -> Box<dyn RoutingStrategy> {
+        match *self {
+            SimMode::Flooding => Box::new(Flooding),
+            SimMode::Swarm => Box::new(Swarm),
+            SimMode::WeightedSplit { k } => Box::new(WeightedSplit { k }),
+            SimMode::Adaptive => Box::new(Adaptive),
+            SimMode::FairSwarm => Box::new(FairSwarm),
+        }
+    }
+}
+
+// Always forward (Dumb)
+pub struct Flooding;
+
+impl RoutingStrategy for Flooding {
+    fn should_forward(&self, _current: &Node, _neighbor: &Node, _packet: &Packet, _rng: &mut SimRng) -> bool {
+        true
+    }
+}
+
+// Infrastructure and dedicated relays always carry; phones are a sparse last resort
+pub struct Swarm;
+
+impl RoutingStrategy for Swarm {
+    fn should_forward(&self, _current: &Node, neighbor: &Node, _packet: &Packet, rng: &mut SimRng) -> bool {
+        if neighbor.node_type == NodeType::BaseStation || neighbor.role == NodeRole::Relay {
+            return true;
+        }
+        // Aggressive Unicorn Logic:
+        // Only relay if battery is high AND random chance is low (sparse routing)
+        // e.g. 0.05 probability if full battery.
+        let bat_p = neighbor.battery_level / neighbor.battery_capacity;
+        rng.random_bool(SWARM_RELAY_PROB * (bat_p as f64))
+    }
+}
+
+// Swarm's battery gate, divided down by how much a phone has relayed lately, so
+// well-placed phones aren't hammered while their neighbors sit idle
+pub struct FairSwarm;
+
+impl RoutingStrategy for FairSwarm {
+    fn select_neighbors(
+        &self,
+        ctx: &RoutingContext,
+        _current: &Node,
+        candidates: &[u32],
+        _packet: &Packet,
+        rng: &mut SimRng,
+    ) -> Option<BTreeSet<u32>> {
+        Some(candidates.iter()
+            .copied()
+            .filter(|&id| {
+                let neighbor = &ctx.nodes[id as usize];
+                if neighbor.node_type == NodeType::BaseStation || neighbor.role == NodeRole::Relay {
+                    return true;
+                }
+                let bat_p = (neighbor.battery_level / neighbor.battery_capacity) as f64;
+                rng.random_bool(SWARM_RELAY_PROB * bat_p / (1.0 + ctx.relay_load[id as usize]))
+            })
+            .collect())
+    }
+
+    fn should_forward(&self, _current: &Node, _neighbor: &Node, _packet: &Packet, _rng: &mut SimRng) -> bool {
+        true // Already picked
+    }
+}
+
+// Fuller batteries and neighbors closer to the target are more likely to be chosen
+pub struct WeightedSplit {
+    pub k: usize,
+}
+
+impl RoutingStrategy for WeightedSplit {
+    fn select_neighbors(
+        &self,
+        ctx: &RoutingContext,
+        current: &Node,
+        candidates: &[u32],
+        packet: &Packet,
+        rng: &mut SimRng,
+    ) -> Option<BTreeSet<u32>> {
+        let hint_fresh = ctx.config.max_hint_age.is_none_or(|age| ctx.step - packet.hint_step <= age);
+        let weighted: Vec<(u32, f64)> = candidates.iter()
+            .map(|&id| {
+                let neighbor = &ctx.nodes[id as usize];
+                let bat_p = (neighbor.battery_level / neighbor.battery_capacity) as f64;
+                if !hint_fresh {
+                    return (id, bat_p); // Stale position: don't steer, just spread load
+                }
+                let to_target = neighbor.distance_to_point(packet.target_hint, ctx.config.distance_metric);
+                (id, bat_p / (1.0 + to_target / current.transmission_range))
+            })
+            .collect();
+        Some(weighted_sample(&weighted, self.k, rng))
+    }
+
+    fn should_forward(&self, _current: &Node, _neighbor: &Node, _packet: &Packet, _rng: &mut SimRng) -> bool {
+        true // Already picked
+    }
+}
+
+// Exploit the best-scoring links, occasionally explore the rest
+pub struct Adaptive;
+
+impl RoutingStrategy for Adaptive {
+    fn select_neighbors(
+        &self,
+        ctx: &RoutingContext,
+        current: &Node,
+        candidates: &[u32],
+        _packet: &Packet,
+        rng: &mut SimRng,
+    ) -> Option<BTreeSet<u32>> {
+        let mut ranked: Vec<(u32, f64)> = candidates.iter()
+            .map(|&id| (id, ctx.link_scores.get(&(current.id, id)).copied().unwrap_or(ADAPTIVE_INITIAL_SCORE)))
+            .collect();
+        ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+        Some(ranked.iter()
+            .enumerate()
+            .filter(|&(rank, _)| rank < ADAPTIVE_FANOUT || rng.random_bool(ADAPTIVE_EXPLORE))
+            .map(|(_, &(id, _))| id)
+            .collect())
+    }
+
+    fn should_forward(&self, _current: &Node, _neighbor: &Node, _packet: &Packet, _rng: &mut SimRng) -> bool {
+        true // Already picked
+    }
+}
@@ -0,0 +1,23 @@
+use serde::Deserialize;
+
+use crate::config::{SimConfig, SimMode};
+
+// A named experiment on disk: the full config plus which modes to compare.
+// Any field left out keeps its SimConfig default.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Scenario {
+    pub modes: Option<Vec<SimMode>>, // None = the usual Flooding vs Swarm benchmark
+    #[serde(flatten)]
+    pub config: SimConfig,
+}
+
+impl Scenario {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        toml::from_str(text).map_err(|e| e.to_string())
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("can't read '{}': {}", path, e))?;
+        Self::parse(&text).map_err(|e| format!("invalid scenario '{}': {}", path, e))
+    }
+}
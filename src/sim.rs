@@ -0,0 +1,1982 @@
+use rand::{Rng, SeedableRng};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::config::*;
+use crate::disaster::{DisasterModel, RegionDisaster};
+use crate::event::{DeathCause, DropReason, SimEvent};
+use crate::graph::MeshGraph;
+use crate::log::{Bounds, ExportError, JsonlWriter, LogExport, LogFormat, MessageTimeline, NodeLog, PacketLog, SimLog, SimLogFile, SCHEMA_VERSION};
+#[cfg(feature = "tokenomics")]
+use crate::log::WalletLog;
+use crate::node::{node_seed, radio_on, Layout, Node, NodeRole, NodeType, Packet, PacketId};
+use crate::routing::{RoutingContext, RoutingStrategy};
+
+// The generator behind every seeded draw. StdRng's algorithm may change between rand
+// releases; the portable-rng feature pins ChaCha8 so a shared seed gives the same run anywhere.
+#[cfg(not(feature = "portable-rng"))]
+pub type SimRng = rand::rngs::StdRng;
+#[cfg(feature = "portable-rng")]
+pub type SimRng = rand_chacha::ChaCha8Rng;
+
+// Recorded with benchmark results, since the same seed means a different run on each backend
+pub const RNG_BACKEND: &str = if cfg!(feature = "portable-rng") { "chacha8" } else { "std" };
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimStats {
+    pub total_energy: f32,
+    pub idle_energy: f32, // Standby drain of every live node
+    pub tx_energy: f32,   // Transmissions
+    pub rx_energy: f32,   // Receptions, including redundant copies
+    pub control_energy: f32, // HELLO beacons (TX and RX) of the overhead model; part of total_energy
+    pub wasted_energy: f32, // TX/RX spent on messages that never got through
+    pub phone_energy_remaining: f32, // Charge left across all smartphones at the end of the run
+    pub success_packets: u32,
+    pub generated_packets: u32, // Messages the source injected
+    pub delivered_bytes: u64, // Payload of each delivered message, counted once
+    pub total_hops: u32,
+    pub target_id: u32,
+    pub target_lost_step: Option<i32>, // Step at which the target died (flow stopped counting)
+    pub settled_step: Option<i32>, // Once traffic: step by which every packet was delivered or dropped
+    pub source_lost_step: Option<i32>, // Step at which the source died (generation stopped)
+    pub network_death_step: Option<i32>, // Last useful step: partitioned for good and nothing delivered after it
+    pub drained_deliveries: u32, // Messages delivered after the source died: pure in-flight drainage
+    pub interference_losses: u32, // Forwards that failed because interference shrank the range
+    pub link_losses: u32, // Copies dropped over weak links
+    pub forwards: u32, // Copies handed to a neighbor: what the relay reward would mint for
+    pub rewards_owed: f32, // The reward schedule summed over every forward, whether or not the mode pays
+    pub redundant_transmissions: u32, // Copies that reached the target after their message was already delivered
+    pub avg_distinct_paths: Option<f64>, // Distinct successful paths per delivered message (path redundancy)
+    pub oscillating_packets: u32, // Messages that wandered far beyond the shortest path
+    pub latencies: Vec<u32>, // Steps from creation to first delivery, per delivered message
+    pub delivery_steps: Vec<i32>, // Step of each message's first delivery, in order
+    pub steps_run: i32,
+    pub sla_fraction: f64, // Share of delivered messages within the SLA deadline
+    pub sla_met: bool,
+    pub deaths_disaster: u32,
+    pub deaths_battery: u32,
+    pub deaths_churn: u32,
+    pub max_queue_depth: usize, // Peak packets waiting at the start of a step's processing
+    pub dropped_overflow: u32, // Oldest copies dropped because the queue hit max_queue_size
+    pub retries: u32, // Application-layer re-sends by the source
+    pub delivered_after_retry: u32, // Messages that only got through on a re-send
+    pub abandoned_messages: u32, // Undelivered messages the source stopped re-sending once too stale
+    pub disaster_impacts: Vec<DisasterImpact>,
+    pub battery_cov: Vec<(i32, f64)>, // (step, CoV of live smartphones' charge) at the end of each step
+    pub active_nodes: Vec<(i32, usize)>, // (step, nodes up) at the end of each step: the attrition curve
+    pub optimal_hops: Option<u32>, // Source -> target BFS distance on the initial graph
+    pub avg_path_stretch: Option<f64>, // Mean delivered hops / optimal hops (peer-to-peer deliveries only)
+    pub delivered_via_backhaul: u32, // Copies that reached a base station other than the target
+    pub backbone_transfers: u32, // Zero-hop jumps between base stations over the backbone
+    pub delivered_p2p: u32, // Copies that reached the target over the mesh
+    pub hotspots: Vec<(u32, u32)>, // (node, delivered paths it relayed), busiest first
+    pub relay_hops_smartphone: u32, // Relay positions on delivered paths held by phones
+    pub relay_hops_base_station: u32, // ...and by base stations
+    pub acks_lost: u32, // Adaptive ACKs that died on a hop with no reverse link
+    pub carried_packets: u32, // Copies a holder kept in its buffer because nobody took them
+    pub carried_deliveries: u32, // Messages first delivered by a copy that was carried somewhere on its way
+    pub carry_overflow: u32, // Copies dropped because the holder's carry buffer was full
+    pub ttl_refreshes: u32, // Packets a base station sent on with a fresh TTL
+    pub beyond_ttl_deliveries: u32, // Copies delivered in more hops than the initial TTL allows
+    pub backward_skips: u32, // Forwards skipped because the neighbor was no closer to the target
+    pub suppressed_duplicates: u32, // Flooding copies dropped because the receiver had already seen the message
+    pub late_bs_deliveries: u32, // Messages whose delivery path used a late-deployed base station
+    pub refused_forwards: u32, // Packets a selfish relay sat on instead of forwarding
+    pub cutoff_refusals: u32, // Packets a low-battery phone declined to relay to save itself
+    pub cutoff_lost: u32, // Undelivered messages that had a copy declined by a low-battery phone
+    pub drop_reasons: BTreeMap<DropReason, u32>, // Undelivered messages by what killed their last copy
+    pub lost_in_flight: u32, // Undelivered messages still queued when the run ended
+    pub packets_in_flight_at_end: usize, // Queued copies the step limit cut off (any message, delivered or not)
+    #[cfg(feature = "tokenomics")]
+    pub tokens_minted: f32, // Relay rewards actually paid out
+    #[cfg(feature = "tokenomics")]
+    pub minted_per_step: Vec<f32>, // Index = step - 1; with halving, shows the incentive drying up
+    #[cfg(feature = "tokenomics")]
+    pub premiums_usdc: f32, // Insurance pool built from relay rewards
+    #[cfg(feature = "tokenomics")]
+    pub payouts_usdc: f32, // Total the oracle paid out
+    #[cfg(feature = "tokenomics")]
+    pub wallets: Vec<WalletLog>, // Every node's final balances, by id
+    pub deferred_packets: u32, // Times a packet waited a step because its holder hit the energy budget
+    pub sleep_deferrals: u32, // Times a packet waited a step because its holder's radio was off
+    pub spectrum_deferrals: u32, // Times a packet waited a step because the mesh-wide airtime was used up
+    pub never_relayed: u32, // Nodes (source and target excluded) that never relayed a packet
+    pub relay_candidates: u32, // Nodes that could have relayed: everyone but source and target
+}
+
+impl SimStats {
+    // Relay rewards it took to deliver each message, as if every mode paid them
+    #[cfg(feature = "tokenomics")]
+    pub fn tokens_per_delivered_message(&self) -> Option<f64> {
+        (!self.latencies.is_empty()).then(|| self.rewards_owed as f64 / self.latencies.len() as f64)
+    }
+
+    // Reserves / liabilities; None while nothing has been paid out
+    #[cfg(feature = "tokenomics")]
+    pub fn solvency_ratio(&self) -> Option<f64> {
+        (self.payouts_usdc > 0.0).then(|| self.premiums_usdc as f64 / self.payouts_usdc as f64)
+    }
+
+    pub fn avg_latency(&self) -> Option<f64> {
+        if self.latencies.is_empty() {
+            return None;
+        }
+        Some(self.latencies.iter().sum::<u32>() as f64 / self.latencies.len() as f64)
+    }
+
+    // Nearest-rank percentile (0-100] of delivery latency; with few samples the
+    // upper percentiles collapse onto the slowest delivery rather than interpolating
+    pub fn latency_percentile(&self, percentile: f64) -> Option<u32> {
+        let mut sorted = self.latencies.clone();
+        sorted.sort_unstable();
+        let rank = (percentile / 100.0 * sorted.len() as f64).ceil() as usize;
+        sorted.get(rank.clamp(1, sorted.len().max(1)) - 1).copied()
+    }
+
+    // Mean gap between consecutive first deliveries
+    pub fn mean_inter_delivery(&self) -> Option<f64> {
+        let (first, last) = (self.delivery_steps.first()?, self.delivery_steps.last()?);
+        (self.delivery_steps.len() > 1).then(|| (last - first) as f64 / (self.delivery_steps.len() - 1) as f64)
+    }
+
+    // (window start step, deliveries per step) over consecutive windows of `window` steps
+    pub fn delivery_rate(&self, window: i32) -> Vec<(i32, f64)> {
+        (1..=self.steps_run)
+            .step_by(window.max(1) as usize)
+            .map(|start| {
+                let end = (start + window - 1).min(self.steps_run);
+                let count = self.delivery_steps.iter().filter(|&&s| (start..=end).contains(&s)).count();
+                (start, count as f64 / (end - start + 1) as f64)
+            })
+            .collect()
+    }
+
+    // Comparison ratios are None rather than NaN/inf when a run spent no energy
+    pub fn deliveries_per_kj(&self) -> Option<f64> {
+        (self.total_energy > 0.0).then(|| self.success_packets as f64 / self.total_energy as f64 * 1000.0)
+    }
+
+    // Useful throughput per unit of energy: mixed-size traffic makes packet counts misleading
+    pub fn bytes_per_joule(&self) -> Option<f64> {
+        (self.total_energy > 0.0).then(|| self.delivered_bytes as f64 / self.total_energy as f64)
+    }
+
+    pub fn energy_saved_vs(&self, baseline: &SimStats) -> Option<f64> {
+        (baseline.total_energy > 0.0)
+            .then(|| (baseline.total_energy - self.total_energy) as f64 / baseline.total_energy as f64 * 100.0)
+    }
+
+    pub fn battery_extension_vs(&self, baseline: &SimStats) -> Option<f64> {
+        (self.total_energy > 0.0).then(|| baseline.total_energy as f64 / self.total_energy as f64)
+    }
+
+    // (mean, peak) of the per-step battery CoV
+    pub fn battery_cov_summary(&self) -> Option<(f64, f64)> {
+        if self.battery_cov.is_empty() {
+            return None;
+        }
+        let mean = self.battery_cov.iter().map(|&(_, cov)| cov).sum::<f64>() / self.battery_cov.len() as f64;
+        let peak = self.battery_cov.iter().map(|&(_, cov)| cov).fold(0.0, f64::max);
+        Some((mean, peak))
+    }
+
+    // Wasted / useful TX+RX energy; None while nothing useful was spent
+    pub fn waste_ratio(&self) -> Option<f64> {
+        let useful = self.tx_energy + self.rx_energy - self.wasted_energy;
+        (useful > 0.0).then(|| self.wasted_energy as f64 / useful as f64)
+    }
+
+    // Unique messages delivered / messages injected
+    pub fn delivery_ratio(&self) -> f64 {
+        if self.generated_packets == 0 { 0.0 } else { self.latencies.len() as f64 / self.generated_packets as f64 }
+    }
+
+    // Share of delivered relaying done by infrastructure rather than peers
+    pub fn base_station_hop_share(&self) -> Option<f64> {
+        let total = self.relay_hops_smartphone + self.relay_hops_base_station;
+        (total > 0).then(|| self.relay_hops_base_station as f64 / total as f64)
+    }
+
+    // Share of the mesh that was dead weight for this flow
+    pub fn never_relayed_fraction(&self) -> f64 {
+        if self.relay_candidates == 0 { 0.0 } else { self.never_relayed as f64 / self.relay_candidates as f64 }
+    }
+}
+
+// How badly a disaster shattered the live graph
+#[derive(Debug, Clone, PartialEq)]
+pub struct DisasterImpact {
+    pub step: i32,
+    pub degree_before: f64,
+    pub degree_after: f64,
+    pub largest_before: usize, // Nodes in the largest (weakly) connected component
+    pub largest_after: usize,
+    pub coverage_before: Option<f64>, // Share of live phones with a path to a live base station
+    pub coverage_after: Option<f64>,
+    pub critical_before: Option<usize>, // Relays whose loss alone cuts source from target; None = already cut
+    pub critical_after: Option<usize>,
+}
+
+// Wall-clock breakdown of one run, for --timing
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RunTimings {
+    pub adjacency: Duration, // MeshGraph construction (range checks within each spatial grid neighborhood)
+    pub stepping: Duration,  // The step loop, including per-step log building
+    pub serialization: Duration, // Encoding and writing the exported log
+}
+
+// What happened during a single call to `Simulation::step`
+#[derive(Debug, Clone)]
+pub struct StepReport {
+    pub step: i32,
+    pub events: Vec<SimEvent>,
+    pub delivered: Vec<PacketLog>, // Paths that reached the target this step
+    pub timelines: Vec<MessageTimeline>, // The first delivered copy of each message, with hop times
+    pub active_nodes: usize,
+    pub source_alive: bool, // At the end of the step; once false, deliveries are drainage
+    pub in_flight: usize, // Packets queued for the next step
+    pub total_energy: f32, // Cumulative energy spent by the end of this step
+}
+
+// Read-only view of the live simulation, e.g. for an external UI
+pub struct SimState<'a> {
+    pub step: i32,
+    pub nodes: &'a [Node],
+    pub packets: &'a VecDeque<Packet>,
+    pub graph: &'a MeshGraph,
+}
+
+// Number of packets the traffic pattern injects at `step`
+pub fn packets_to_generate(pattern: TrafficPattern, step: i32, rng: &mut impl Rng) -> u32 {
+    match pattern {
+        TrafficPattern::Constant(rate) => rate,
+        TrafficPattern::Burst { start, end, rate } => {
+            if (start..=end).contains(&step) { rate } else { 1 }
+        }
+        TrafficPattern::Once { count } => if step == 1 { count } else { 0 },
+        TrafficPattern::Poisson { lambda } => {
+            // Knuth: multiply uniforms until the product drops below e^-lambda
+            let limit = (-lambda).exp();
+            let mut count = 0;
+            let mut product: f64 = rng.random();
+            while product > limit {
+                count += 1;
+                product *= rng.random::<f64>();
+            }
+            count
+        }
+    }
+}
+
+// BFS hop count from `source` to every node over the current active graph
+pub fn hop_distances(nodes: &[Node], source: u32) -> Vec<Option<u32>> {
+    let mut dist = vec![None; nodes.len()];
+    if !nodes[source as usize].is_active {
+        return dist;
+    }
+    dist[source as usize] = Some(0);
+    let mut queue = VecDeque::from([source]);
+    while let Some(id) = queue.pop_front() {
+        let next = dist[id as usize].unwrap() + 1;
+        for &peer in &nodes[id as usize].peers {
+            if dist[peer as usize].is_none() && nodes[peer as usize].is_active {
+                dist[peer as usize] = Some(next);
+                queue.push_back(peer);
+            }
+        }
+    }
+    dist
+}
+
+// Weighted sampling without replacement: up to `k` ids, each draw proportional to weight
+pub fn weighted_sample(candidates: &[(u32, f64)], k: usize, rng: &mut impl Rng) -> BTreeSet<u32> {
+    let mut pool: Vec<(u32, f64)> = candidates.iter().copied().filter(|&(_, w)| w > 0.0).collect();
+    let mut picked = BTreeSet::new();
+    while picked.len() < k && !pool.is_empty() {
+        let total: f64 = pool.iter().map(|&(_, w)| w).sum();
+        let mut r = rng.random_range(0.0..total);
+        let mut index = pool.len() - 1;
+        for (i, &(_, w)) in pool.iter().enumerate() {
+            if r < w {
+                index = i;
+                break;
+            }
+            r -= w;
+        }
+        picked.insert(pool.swap_remove(index).0);
+    }
+    picked
+}
+
+pub fn select_target(nodes: &[Node], source_id: u32, config: &SimConfig) -> u32 {
+    let metric = config.distance_metric;
+    let last_node = nodes.len() as u32 - 1;
+    match config.target {
+        // Drawn together with the source by `random_pair`; the legacy target is the fallback
+        TargetSelection::LastNode | TargetSelection::RandomPair => last_node,
+        TargetSelection::ById(id) => id,
+        TargetSelection::NearestBaseStation => {
+            let source = &nodes[source_id as usize];
+            let nearest = |survivors_only: bool| {
+                nodes.iter()
+                    .filter(|n| n.node_type == NodeType::BaseStation && n.id != source_id && n.is_active)
+                    .filter(|n| !survivors_only || !config.disaster_regions().iter().any(|r| r.covers(n.position)))
+                    .min_by(|a, b| source.distance_to(a, metric).total_cmp(&source.distance_to(b, metric)))
+                    .map(|n| n.id)
+            };
+            // Prefer a station the disaster won't hit; fall back to any station, then the legacy target.
+            nearest(true).or_else(|| nearest(false)).unwrap_or_else(|| {
+                narrate!("⚠️  No base station available as target, falling back to node {}", last_node);
+                last_node
+            })
+        }
+    }
+}
+
+// Coefficient of variation of live smartphones' state of charge. Charge is a
+// fraction of capacity so phones with different batteries compare fairly; 0 =
+// perfectly even drain. None without live phones or once they're all empty.
+pub fn battery_cov(nodes: &[Node]) -> Option<f64> {
+    let charges: Vec<f64> = nodes.iter()
+        .filter(|n| n.is_active && n.node_type == NodeType::Smartphone)
+        .map(|n| (n.battery_level / n.battery_capacity) as f64)
+        .collect();
+    if charges.is_empty() {
+        return None;
+    }
+    let mean = charges.iter().sum::<f64>() / charges.len() as f64;
+    if mean <= 0.0 {
+        return None;
+    }
+    let variance = charges.iter().map(|c| (c - mean).powi(2)).sum::<f64>() / charges.len() as f64;
+    Some(variance.sqrt() / mean)
+}
+
+// Each node's coverage cell: the nearest live base station, which is its own
+// cell. None for everyone while no station is up.
+pub fn nearest_base_stations(nodes: &[Node], metric: DistanceMetric) -> Vec<Option<u32>> {
+    let stations: Vec<&Node> = nodes.iter()
+        .filter(|n| n.node_type == NodeType::BaseStation && n.is_active)
+        .collect();
+    nodes.iter()
+        .map(|node| {
+            stations.iter()
+                .min_by(|a, b| node.distance_to(a, metric).total_cmp(&node.distance_to(b, metric)).then(a.id.cmp(&b.id)))
+                .map(|station| station.id)
+        })
+        .collect()
+}
+
+// Jain's fairness index over per-flow delivery ratios: 1.0 = every flow served
+// equally, 1/n = one flow got everything. None when no flow got anything.
+pub fn jain_fairness(ratios: &[f64]) -> Option<f64> {
+    let sum: f64 = ratios.iter().sum();
+    let sum_sq: f64 = ratios.iter().map(|r| r * r).sum();
+    (sum_sq > 0.0).then(|| sum * sum / (ratios.len() as f64 * sum_sq))
+}
+
+static NARRATE_TO_STDERR: AtomicBool = AtomicBool::new(false);
+
+// Send the run narration (alerts, progress, results) to stderr instead of stdout,
+// e.g. while stdout carries a JSON stream. Process-wide, like stdout itself.
+pub fn narrate_to_stderr(enabled: bool) {
+    NARRATE_TO_STDERR.store(enabled, Ordering::Relaxed);
+}
+
+#[doc(hidden)]
+pub fn narrating_to_stderr() -> bool {
+    NARRATE_TO_STDERR.load(Ordering::Relaxed)
+}
+
+// Two-sided 95% Student's t critical values for 1..=30 degrees of freedom
+const T_95: [f64; 30] = [
+    12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228,
+    2.201, 2.179, 2.160, 2.145, 2.131, 2.120, 2.110, 2.101, 2.093, 2.086,
+    2.080, 2.074, 2.069, 2.064, 2.060, 2.056, 2.052, 2.048, 2.045, 2.042,
+];
+
+// (mean, half-width) of the 95% confidence interval for the mean of independent
+// samples, e.g. one per seed. Past 30 degrees of freedom the nearest tabulated
+// value below is used, which only ever widens the interval. None under 2 samples.
+pub fn confidence_interval_95(samples: &[f64]) -> Option<(f64, f64)> {
+    let n = samples.len();
+    if n < 2 {
+        return None;
+    }
+    let mean = samples.iter().sum::<f64>() / n as f64;
+    let variance = samples.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1) as f64;
+    let t = match n - 1 {
+        df @ 1..=30 => T_95[df - 1],
+        31..=39 => 2.042,
+        40..=59 => 2.021,
+        60..=119 => 2.000,
+        120..=999 => 1.980,
+        _ => 1.960,
+    };
+    Some((mean, t * (variance / n as f64).sqrt()))
+}
+
+// Seeded (source, target) draw among the nodes no exclusion rejects
+pub fn random_pair(nodes: &[Node], rng: &mut impl Rng, exclusions: &[&dyn Fn(&Node) -> bool]) -> Option<(u32, u32)> {
+    let eligible: Vec<u32> = nodes.iter()
+        .filter(|&n| !exclusions.iter().any(|excluded| excluded(n)))
+        .map(|n| n.id)
+        .collect();
+    if eligible.len() < 2 {
+        return None;
+    }
+    let picked = rand::seq::index::sample(rng, eligible.len(), 2);
+    Some((eligible[picked.index(0)], eligible[picked.index(1)]))
+}
+
+pub struct Simulation {
+    mode: SimMode,
+    strategy: Box<dyn RoutingStrategy>, // mode.strategy() unless replaced via with_strategy
+    disasters: Vec<Box<dyn DisasterModel>>, // One per configured region unless replaced via with_disasters
+    config: SimConfig,
+    nodes: Vec<Node>,
+    graph: MeshGraph,
+    packet_queue: VecDeque<Packet>,
+    rng: SimRng, // Topology, traffic and disaster draws
+    node_rngs: Vec<SimRng>, // Per-node forwarding/churn decisions, indexed by node id
+    master_seed: u64, // Seeds the duty-cycle schedule
+    step: i32,
+    start_node_id: u32,
+    target_node_id: u32,
+    total_energy_consumed: f32,
+    idle_energy: f32,
+    tx_energy: f32,
+    rx_energy: f32,
+    message_energy: BTreeMap<String, f32>, // TX/RX per message id, across all its copies
+    delivered_bytes: u64,
+    control_energy: f32,
+    next_seq: u64, // Sequence number of the next generated message
+    message_fates: BTreeMap<String, DropReason>, // Latest copy drop per message id
+    deaths: BTreeMap<u32, (DeathCause, i32)>, // Most recent death of each node, and its step
+    delivered_paths: BTreeMap<String, BTreeSet<Vec<u32>>>, // Distinct successful histories per message id
+    successful_packets: u32,
+    generated_packets: u32,
+    total_hops: u32,
+    #[cfg(feature = "tokenomics")]
+    disaster_triggered: bool,
+    #[cfg(feature = "tokenomics")]
+    oracle_paid: Vec<bool>, // Per disaster model, each its own policy
+    #[cfg(feature = "tokenomics")]
+    killed_by: Vec<BTreeSet<u32>>, // Every id each disaster model's apply returned so far
+    target_lost_step: Option<i32>,
+    source_lost_step: Option<i32>,
+    settled_step: Option<i32>,
+    drained_deliveries: u32,
+    interference_losses: u32,
+    link_losses: u32,
+    forwards: u32,
+    rewards_owed: f32,
+    delivered_ids: HashSet<String>, // Membership only, never iterated
+    redundant_transmissions: u32,
+    shortest_hops: Vec<Option<u32>>, // From the source, on the initial graph
+    oscillating_ids: HashSet<String>,
+    partitioned: bool,
+    partitioned_since: Option<i32>, // Start of the current source -> target partition
+    latencies: Vec<u32>,
+    delivery_steps: Vec<i32>,
+    churned: Vec<bool>, // Offline due to churn (recoverable), indexed by node id
+    deaths_disaster: u32,
+    deaths_battery: u32,
+    deaths_churn: u32,
+    max_queue_depth: usize,
+    battery_cov: Vec<(i32, f64)>,
+    active_nodes: Vec<(i32, usize)>,
+    disaster_impacts: Vec<DisasterImpact>,
+    // Undelivered messages the source may still re-send: (original, re-sends so far, last send step)
+    outstanding: BTreeMap<String, (Packet, u32, i32)>,
+    retries: u32,
+    delivered_after_retry: u32,
+    abandoned_messages: u32,
+    stretch_sum: f64,
+    delivered_via_backhaul: u32,
+    backbone_transfers: u32,
+    delivered_p2p: u32,
+    path_appearances: BTreeMap<u32, u32>, // Intermediate-hop count per node over delivered paths
+    refused_forwards: u32,
+    cutoff_refusals: u32,
+    cutoff_ids: BTreeSet<String>, // Messages with at least one copy declined by a low-battery phone
+    #[cfg(feature = "tokenomics")]
+    minted_per_step: Vec<f32>,
+    #[cfg(feature = "tokenomics")]
+    premiums_usdc: f32,
+    #[cfg(feature = "tokenomics")]
+    payouts_usdc: f32,
+    deferred_packets: u32,
+    sleep_deferrals: u32,
+    dropped_overflow: u32,
+    spectrum_deferrals: u32,
+    link_scores: BTreeMap<(u32, u32), f64>, // Adaptive: EMA delivery success per (node, neighbor)
+    relay_load: Vec<f64>, // FairSwarm: relays per node, decayed by fair_load_decay each step
+    acks_lost: u32,
+    seen: Vec<HashMap<String, i32>>, // Flood suppression: message ids each node has held and when, indexed by node id
+    suppressed_duplicates: u32,
+    backward_skips: u32,
+    late_bs_deliveries: u32,
+    relay_hops_smartphone: u32,
+    relay_hops_base_station: u32,
+    ttl_refreshes: u32,
+    beyond_ttl_deliveries: u32,
+    carried_packets: u32,
+    carried_deliveries: u32,
+    carry_overflow: u32,
+    adjacency_time: Duration,
+}
+
+impl Simulation {
+    // Panics where try_new would fail; configs that pass validate() never do
+    pub fn new(mode: SimMode, config: &SimConfig) -> Self {
+        Self::try_new(mode, config).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    // Err(TargetIsSource) when source and target land on the same node
+    pub fn try_new(mode: SimMode, config: &SimConfig) -> Result<Self, ConfigError> {
+        // With a seed both modes see the exact same topology and every run
+        // reproduces; without one each run draws fresh entropy.
+        let mut rng = match config.seed {
+            Some(seed) => SimRng::seed_from_u64(seed),
+            None => SimRng::from_os_rng(),
+        };
+        let layout = Layout::new(config, &mut rng);
+        let nodes: Vec<Node> = (0..config.node_count).map(|id| Node::new(id, config, &layout, &mut rng)).collect();
+        Self::with_nodes(mode, config, nodes, rng)
+    }
+
+    // Hand-crafted topology (ids must be 0..len, in order); peers are rebuilt from positions.
+    // Panics where try_from_nodes would fail.
+    pub fn from_nodes(mode: SimMode, config: &SimConfig, nodes: Vec<Node>) -> Self {
+        Self::try_from_nodes(mode, config, nodes).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    pub fn try_from_nodes(mode: SimMode, config: &SimConfig, nodes: Vec<Node>) -> Result<Self, ConfigError> {
+        let rng = match config.seed {
+            Some(seed) => SimRng::seed_from_u64(seed),
+            None => SimRng::from_os_rng(),
+        };
+        Self::with_nodes(mode, config, nodes, rng)
+    }
+
+    fn with_nodes(mode: SimMode, config: &SimConfig, mut nodes: Vec<Node>, mut rng: SimRng) -> Result<Self, ConfigError> {
+        // Child streams: one node's decisions don't shift when another node handles more packets
+        let master_seed = config.seed.unwrap_or_else(|| rng.random());
+        let node_rngs = (0..nodes.len() as u32).map(|id| SimRng::seed_from_u64(node_seed(master_seed, id))).collect();
+
+        // Rebuild Adjacency
+        let adjacency_start = Instant::now();
+        let graph = MeshGraph::build(&nodes, config.distance_metric);
+        let adjacency_time = adjacency_start.elapsed();
+        narrate!("🔗 {} links, {} one-way", graph.link_count(), graph.one_way_link_count());
+        for node in &mut nodes {
+            node.peers = graph.neighbors(node.id).to_vec();
+        }
+
+        // Late stations start dark
+        for node in &mut nodes {
+            if node.activation_step > 1 {
+                node.is_active = false;
+            }
+        }
+        let (start_node_id, target_node_id) = match config.target {
+            TargetSelection::RandomPair => {
+                let zones: Vec<DisasterRegion> = config.disaster_regions().into_iter().filter(DisasterRegion::is_zone).collect();
+                let exclusions: [&dyn Fn(&Node) -> bool; 3] = [
+                    &|n| n.node_type == NodeType::BaseStation,
+                    &|n| !n.is_active,
+                    // A zone kills the pair at DISASTER_STEP; the other disasters spare the target instead
+                    &|n| zones.iter().any(|zone| zone.covers(n.position)),
+                ];
+                random_pair(&nodes, &mut rng, &exclusions).unwrap_or_else(|| {
+                    narrate!("⚠️  No valid random pair, falling back to node 0 -> {}", nodes.len() - 1);
+                    (0, nodes.len() as u32 - 1)
+                })
+            }
+            _ => {
+                // The source is where the flow starts, so it's always up
+                nodes[0].activation_step = 0;
+                nodes[0].is_active = true;
+                (0, select_target(&nodes, 0, config))
+            }
+        };
+        // A zero-hop "delivery" isn't a result; validate() rejects the configs that would get here
+        if start_node_id == target_node_id {
+            return Err(ConfigError::TargetIsSource(target_node_id));
+        }
+        narrate!("📍 Source: node {}", start_node_id);
+        narrate!("🎯 Target: node {} ({:?})", target_node_id, nodes[target_node_id as usize].node_type);
+        let shortest_hops = hop_distances(&nodes, start_node_id);
+        let churned = vec![false; nodes.len()];
+        let seen = vec![HashMap::new(); nodes.len()];
+        let relay_load = vec![0.0; nodes.len()];
+        let disasters = RegionDisaster::from_config(config, target_node_id).into_iter()
+            .map(|model| Box::new(model) as Box<dyn DisasterModel>)
+            .collect();
+
+        Ok(Simulation {
+            mode,
+            strategy: mode.strategy(),
+            disasters,
+            config: config.clone(),
+            nodes,
+            graph,
+            packet_queue: VecDeque::new(),
+            rng,
+            node_rngs,
+            master_seed,
+            step: 0,
+            start_node_id,
+            target_node_id,
+            total_energy_consumed: 0.0,
+            idle_energy: 0.0,
+            tx_energy: 0.0,
+            rx_energy: 0.0,
+            message_energy: BTreeMap::new(),
+            delivered_bytes: 0,
+            control_energy: 0.0,
+            next_seq: 0,
+            message_fates: BTreeMap::new(),
+            deaths: BTreeMap::new(),
+            delivered_paths: BTreeMap::new(),
+            successful_packets: 0,
+            generated_packets: 0,
+            total_hops: 0,
+            #[cfg(feature = "tokenomics")]
+            disaster_triggered: false,
+            #[cfg(feature = "tokenomics")]
+            oracle_paid: vec![false; config.disaster_regions().len()],
+            #[cfg(feature = "tokenomics")]
+            killed_by: vec![BTreeSet::new(); config.disaster_regions().len()],
+            target_lost_step: None,
+            source_lost_step: None,
+            settled_step: None,
+            drained_deliveries: 0,
+            interference_losses: 0,
+            link_losses: 0,
+            forwards: 0,
+            rewards_owed: 0.0,
+            delivered_ids: HashSet::new(),
+            redundant_transmissions: 0,
+            shortest_hops,
+            oscillating_ids: HashSet::new(),
+            partitioned: false,
+            partitioned_since: None,
+            latencies: Vec::new(),
+            delivery_steps: Vec::new(),
+            churned,
+            deaths_disaster: 0,
+            deaths_battery: 0,
+            deaths_churn: 0,
+            max_queue_depth: 0,
+            battery_cov: Vec::new(),
+            active_nodes: Vec::new(),
+            disaster_impacts: Vec::new(),
+            outstanding: BTreeMap::new(),
+            retries: 0,
+            delivered_after_retry: 0,
+            abandoned_messages: 0,
+            stretch_sum: 0.0,
+            delivered_via_backhaul: 0,
+            backbone_transfers: 0,
+            delivered_p2p: 0,
+            path_appearances: BTreeMap::new(),
+            refused_forwards: 0,
+            cutoff_refusals: 0,
+            cutoff_ids: BTreeSet::new(),
+            #[cfg(feature = "tokenomics")]
+            minted_per_step: Vec::new(),
+            #[cfg(feature = "tokenomics")]
+            premiums_usdc: 0.0,
+            #[cfg(feature = "tokenomics")]
+            payouts_usdc: 0.0,
+            deferred_packets: 0,
+            sleep_deferrals: 0,
+            dropped_overflow: 0,
+            spectrum_deferrals: 0,
+            link_scores: BTreeMap::new(),
+            relay_load,
+            acks_lost: 0,
+            seen,
+            suppressed_duplicates: 0,
+            backward_skips: 0,
+            late_bs_deliveries: 0,
+            relay_hops_smartphone: 0,
+            relay_hops_base_station: 0,
+            ttl_refreshes: 0,
+            beyond_ttl_deliveries: 0,
+            carried_packets: 0,
+            carried_deliveries: 0,
+            carry_overflow: 0,
+            adjacency_time,
+        })
+    }
+
+    #[cfg(feature = "tokenomics")]
+    pub fn oracle_alert_sent(&self) -> bool {
+        self.oracle_paid.iter().any(|&paid| paid)
+    }
+
+    // (source, target) of the benchmark flow
+    pub fn endpoints(&self) -> (u32, u32) {
+        (self.start_node_id, self.target_node_id)
+    }
+
+    // Route with a custom strategy; the mode still decides tokenomics, message ids and Adaptive's ACKs
+    pub fn with_strategy(mut self, strategy: Box<dyn RoutingStrategy>) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    // Strike with custom disaster models instead of the configured regions
+    pub fn with_disasters(mut self, disasters: Vec<Box<dyn DisasterModel>>) -> Self {
+        #[cfg(feature = "tokenomics")]
+        {
+            self.oracle_paid = vec![false; disasters.len()];
+            self.killed_by = vec![BTreeSet::new(); disasters.len()];
+        }
+        self.disasters = disasters;
+        self
+    }
+
+    // Initial topology, for offline analysis (components, degrees, ...)
+    pub fn graph(&self) -> &MeshGraph {
+        &self.graph
+    }
+
+    pub fn is_finished(&self) -> bool {
+        // A one-off burst is over once nothing is left in flight
+        if self.settled_step.is_some() {
+            return true;
+        }
+        if self.step < self.config.max_steps {
+            return false;
+        }
+        // Past the generation cutoff: optionally let in-flight packets drain
+        !self.config.run_until_empty || self.packet_queue.is_empty() || self.step >= DRAIN_SAFETY_CAP
+    }
+
+    pub fn state(&self) -> SimState<'_> {
+        SimState {
+            step: self.step,
+            nodes: &self.nodes,
+            packets: &self.packet_queue,
+            graph: &self.graph,
+        }
+    }
+
+    fn optimal_hops(&self) -> Option<u32> {
+        self.shortest_hops[self.target_node_id as usize]
+    }
+
+    // Drop-oldest: the front of the queue has waited longest, or sits at the back once
+    // the queue is ordered by importance
+    fn trim_queue(&mut self) {
+        if let Some(max) = self.config.max_queue_size {
+            while self.packet_queue.len() > max {
+                let oldest = if self.config.importance_decay.is_some() {
+                    self.packet_queue.pop_back()
+                } else {
+                    self.packet_queue.pop_front()
+                };
+                if let Some(packet) = oldest {
+                    self.message_fates.insert(packet.id, DropReason::Congestion);
+                }
+                self.dropped_overflow += 1;
+            }
+        }
+    }
+
+    // Undelivered messages with a copy still queued: not lost yet, just out of time
+    fn lost_in_flight(&self) -> BTreeSet<&String> {
+        self.packet_queue.iter().map(|p| &p.id).filter(|id| !self.delivered_ids.contains(*id)).collect()
+    }
+
+    fn drop_reasons(&self) -> BTreeMap<DropReason, u32> {
+        let in_flight = self.lost_in_flight();
+        let mut reasons = BTreeMap::new();
+        for (id, &reason) in &self.message_fates {
+            if !self.delivered_ids.contains(id) && !in_flight.contains(id) {
+                *reasons.entry(reason).or_default() += 1;
+            }
+        }
+        reasons
+    }
+
+    // Why the holder of a copy is down: either it died earlier, or during this step
+    fn dead_holder(&self, id: u32, step: i32, was_active: bool, died_now: Option<DeathCause>) -> DropReason {
+        let (cause, step) = match self.deaths.get(&id) {
+            Some(&death) if !was_active => death,
+            _ => (died_now.unwrap_or(DeathCause::Battery), step),
+        };
+        DropReason::DeadHolder { cause, step }
+    }
+
+    // Ties broken by id so the ranking is stable
+    fn hotspots(&self) -> Vec<(u32, u32)> {
+        let mut ranked: Vec<(u32, u32)> = self.path_appearances.iter().map(|(&id, &count)| (id, count)).collect();
+        ranked.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        ranked
+    }
+
+    pub fn stats(&self) -> SimStats {
+        let relay_candidates: Vec<&Node> = self.nodes.iter()
+            .filter(|n| n.id != self.start_node_id && n.id != self.target_node_id)
+            .collect();
+        let within = self.latencies.iter().filter(|&&l| l <= self.config.sla_deadline_steps).count();
+        let sla_fraction = if self.latencies.is_empty() { 0.0 } else { within as f64 / self.latencies.len() as f64 };
+        SimStats {
+            total_energy: self.total_energy_consumed,
+            idle_energy: self.idle_energy,
+            tx_energy: self.tx_energy,
+            rx_energy: self.rx_energy,
+            wasted_energy: self.message_energy.iter()
+                .filter(|(id, _)| !self.delivered_ids.contains(*id))
+                .map(|(_, energy)| energy)
+                .sum(),
+            phone_energy_remaining: self.nodes.iter()
+                .filter(|n| n.node_type == NodeType::Smartphone)
+                .map(|n| n.battery_level)
+                .sum(),
+            success_packets: self.successful_packets,
+            generated_packets: self.generated_packets,
+            delivered_bytes: self.delivered_bytes,
+            control_energy: self.control_energy,
+            total_hops: self.total_hops,
+            target_id: self.target_node_id,
+            target_lost_step: self.target_lost_step,
+            source_lost_step: self.source_lost_step,
+            // Copies already past the cut can still land, so death is the later of the two
+            network_death_step: self.partitioned_since
+                .map(|since| since.max(self.delivery_steps.last().copied().unwrap_or(since))),
+            settled_step: self.settled_step,
+            drained_deliveries: self.drained_deliveries,
+            interference_losses: self.interference_losses,
+            link_losses: self.link_losses,
+            forwards: self.forwards,
+            rewards_owed: self.rewards_owed,
+            redundant_transmissions: self.redundant_transmissions,
+            avg_distinct_paths: (!self.delivered_paths.is_empty()).then(|| {
+                self.delivered_paths.values().map(BTreeSet::len).sum::<usize>() as f64 / self.delivered_paths.len() as f64
+            }),
+            oscillating_packets: self.oscillating_ids.len() as u32,
+            latencies: self.latencies.clone(),
+            delivery_steps: self.delivery_steps.clone(),
+            steps_run: self.step,
+            sla_fraction,
+            sla_met: !self.latencies.is_empty() && sla_fraction >= self.config.sla_target,
+            deaths_disaster: self.deaths_disaster,
+            deaths_battery: self.deaths_battery,
+            deaths_churn: self.deaths_churn,
+            max_queue_depth: self.max_queue_depth,
+            dropped_overflow: self.dropped_overflow,
+            battery_cov: self.battery_cov.clone(),
+            active_nodes: self.active_nodes.clone(),
+            disaster_impacts: self.disaster_impacts.clone(),
+            retries: self.retries,
+            delivered_after_retry: self.delivered_after_retry,
+            abandoned_messages: self.abandoned_messages,
+            optimal_hops: self.optimal_hops(),
+            avg_path_stretch: (self.delivered_p2p > 0 && self.optimal_hops().is_some_and(|h| h > 0))
+                .then(|| self.stretch_sum / self.delivered_p2p as f64),
+            delivered_via_backhaul: self.delivered_via_backhaul,
+            backbone_transfers: self.backbone_transfers,
+            delivered_p2p: self.delivered_p2p,
+            hotspots: self.hotspots(),
+            acks_lost: self.acks_lost,
+            suppressed_duplicates: self.suppressed_duplicates,
+            backward_skips: self.backward_skips,
+            late_bs_deliveries: self.late_bs_deliveries,
+            relay_hops_smartphone: self.relay_hops_smartphone,
+            relay_hops_base_station: self.relay_hops_base_station,
+            ttl_refreshes: self.ttl_refreshes,
+            beyond_ttl_deliveries: self.beyond_ttl_deliveries,
+            carried_packets: self.carried_packets,
+            carried_deliveries: self.carried_deliveries,
+            carry_overflow: self.carry_overflow,
+            refused_forwards: self.refused_forwards,
+            cutoff_refusals: self.cutoff_refusals,
+            cutoff_lost: self.cutoff_ids.iter().filter(|id| !self.delivered_ids.contains(*id)).count() as u32,
+            drop_reasons: self.drop_reasons(),
+            lost_in_flight: self.lost_in_flight().len() as u32,
+            packets_in_flight_at_end: self.packet_queue.len(),
+            #[cfg(feature = "tokenomics")]
+            tokens_minted: self.minted_per_step.iter().sum(),
+            #[cfg(feature = "tokenomics")]
+            minted_per_step: self.minted_per_step.clone(),
+            #[cfg(feature = "tokenomics")]
+            premiums_usdc: self.premiums_usdc,
+            #[cfg(feature = "tokenomics")]
+            payouts_usdc: self.payouts_usdc,
+            #[cfg(feature = "tokenomics")]
+            wallets: self.nodes.iter().map(|n| WalletLog { node_id: n.id, wallet: n.wallet.clone() }).collect(),
+            deferred_packets: self.deferred_packets,
+            sleep_deferrals: self.sleep_deferrals,
+            spectrum_deferrals: self.spectrum_deferrals,
+            never_relayed: relay_candidates.iter().filter(|n| n.packets_relayed == 0).count() as u32,
+            relay_candidates: relay_candidates.len() as u32,
+        }
+    }
+
+    // The ACK walks the delivered path backwards, moving each hop's score towards
+    // success. It needs the reverse link; where that doesn't exist it's lost.
+    fn acknowledge(&mut self, path: &[u32]) {
+        for hop in path.windows(2).rev() {
+            let (from, to) = (hop[0], hop[1]);
+            if !self.graph.has_link(to, from) {
+                self.acks_lost += 1;
+                return;
+            }
+            // The forward already applied the (1 - alpha) decay; adding alpha completes the EMA
+            let score = self.link_scores.entry((from, to)).or_insert(ADAPTIVE_INITIAL_SCORE);
+            *score = (*score + ADAPTIVE_ALPHA).min(1.0);
+        }
+    }
+
+    // Live base station nearest the target, if the packet sits at a different live
+    // station that is farther away and the exit isn't already on its path
+    fn backbone_exit(&self, packet: &Packet) -> Option<u32> {
+        let holder = &self.nodes[*packet.history.last()? as usize];
+        if holder.node_type != NodeType::BaseStation || !holder.is_active {
+            return None;
+        }
+        let metric = self.config.distance_metric;
+        let target = self.nodes[packet.target_id as usize].position;
+        let exit = self.nodes.iter()
+            .filter(|n| n.node_type == NodeType::BaseStation && n.is_active)
+            .min_by(|a, b| a.distance_to_point(target, metric).total_cmp(&b.distance_to_point(target, metric)))?;
+        let closer = exit.distance_to_point(target, metric) < holder.distance_to_point(target, metric);
+        (closer && !packet.history.contains(&exit.id)).then_some(exit.id)
+    }
+
+    // Whether handing `packet` from `from` to `to` gets it closer to the target's last
+    // known spot or to a live base station (and so the internet)
+    fn makes_progress(&self, packet: &Packet, from: u32, to: u32) -> bool {
+        let metric = self.config.distance_metric;
+        let (from, to) = (&self.nodes[from as usize], &self.nodes[to as usize]);
+        let to_station = |node: &Node| self.nodes.iter()
+            .filter(|n| n.node_type == NodeType::BaseStation && n.is_active)
+            .map(|station| node.distance_to_point(station.position, metric))
+            .fold(f64::INFINITY, f64::min);
+        to.distance_to_point(packet.target_hint, metric) < from.distance_to_point(packet.target_hint, metric)
+            || to_station(to) < to_station(from)
+    }
+
+    // Parametric insurance: each disaster model is its own policy, paying the nodes
+    // it killed once their survival drops far enough
+    #[cfg(feature = "tokenomics")]
+    fn run_oracle(&mut self, step: i32, current_step_events: &mut Vec<SimEvent>) {
+        for index in 0..self.disasters.len() {
+            if !self.oracle_paid[index] {
+                self.run_policy(index, step, current_step_events);
+            }
+        }
+    }
+
+    #[cfg(feature = "tokenomics")]
+    fn run_policy(&mut self, index: usize, step: i32, current_step_events: &mut Vec<SimEvent>) {
+        let insured = &self.killed_by[index];
+        // Calculate survival rate from who is actually up, so victims that came back count as survivors
+        let south_total = insured.len();
+        let south_active = insured.iter().filter(|&&id| self.nodes[id as usize].is_active).count();
+        let survival_rate = if south_total > 0 { south_active as f64 / south_total as f64 } else { 1.0 };
+        if south_total > 0 && survival_rate <= self.config.survival_threshold {
+            narrate!("[ORACLE] 💸 INSURANCE TRIGGERED! Paying out USDC to victims...");
+            self.oracle_paid[index] = true;
+
+            // Payout Logic (parametric: optionally proportional to how bad it got)
+            let amount_usdc = if self.config.payout_scaling {
+                INSURANCE_PAYOUT * (1.0 - survival_rate) as f32
+            } else {
+                INSURANCE_PAYOUT
+            };
+            let recipients = insured.len() as u32;
+            for &id in insured {
+                self.nodes[id as usize].wallet.balance_usdc += amount_usdc;
+            }
+            self.payouts_usdc += amount_usdc * recipients as f32;
+            current_step_events.push(SimEvent::OraclePayout { step, recipients, amount_usdc });
+        }
+    }
+
+    // Advance the simulation by one step
+    pub fn step(&mut self) -> StepReport {
+        self.step += 1;
+        let step = self.step;
+        let mode = self.mode;
+        let start_node_id = self.start_node_id;
+        let target_node_id = self.target_node_id;
+        let mut current_step_events: Vec<SimEvent> = Vec::new();
+        let was_active: Vec<bool> = self.nodes.iter().map(|n| n.is_active).collect();
+        #[cfg(feature = "tokenomics")]
+        self.minted_per_step.push(0.0);
+        let mut death_causes: BTreeMap<u32, DeathCause> = BTreeMap::new();
+
+        // 0. Deployments: responders switch on late base stations
+        for node in &mut self.nodes {
+            if node.activation_step == step && step > 1 {
+                node.is_active = true;
+                narrate!("📡 Base station {} online", node.id);
+                current_step_events.push(SimEvent::BaseStationOnline { step, node_id: node.id });
+            }
+        }
+
+        // 1. Disaster: every model gets its say each step (the configured regions strike at DISASTER_STEP)
+        let up_before: Vec<bool> = self.nodes.iter().map(|n| n.is_active).collect();
+        let mut struck: BTreeSet<u32> = BTreeSet::new();
+        for index in 0..self.disasters.len() {
+            let killed = self.disasters[index].apply(&mut self.nodes, step, &mut self.rng);
+            #[cfg(feature = "tokenomics")]
+            self.killed_by[index].extend(killed.iter().copied());
+            struck.extend(killed);
+        }
+        if !struck.is_empty() {
+            narrate!("⚠️  ALERT: DISASTER OCCURRED!");
+            let mut destroyed_count = 0;
+            for &id in &struck {
+                if up_before[id as usize] {
+                    death_causes.insert(id, DeathCause::Disaster);
+                    destroyed_count += 1;
+                }
+                // Offline phones it hit are gone for good, not churned
+                self.churned[id as usize] = false;
+            }
+            narrate!("🔥 {} nodes destroyed.", destroyed_count);
+            let before = self.graph.survivors_where(|id| up_before[id as usize]);
+            let after = self.graph.survivors(&self.nodes);
+            let impact = DisasterImpact {
+                step,
+                degree_before: before.avg_degree(),
+                degree_after: after.avg_degree(),
+                largest_before: before.largest_component(),
+                largest_after: after.largest_component(),
+                coverage_before: before.base_station_coverage(&self.nodes),
+                coverage_after: after.base_station_coverage(&self.nodes),
+                critical_before: before.critical_relays(start_node_id, target_node_id).map(|relays| relays.len()),
+                critical_after: after.critical_relays(start_node_id, target_node_id).map(|relays| relays.len()),
+            };
+            narrate!("🕸️  Avg degree {:.2} -> {:.2}, largest component {} -> {} nodes",
+                impact.degree_before, impact.degree_after, impact.largest_before, impact.largest_after);
+            if let (Some(before), Some(after)) = (impact.coverage_before, impact.coverage_after) {
+                narrate!("📶 Base station coverage {:.0}% -> {:.0}% of live phones", before * 100.0, after * 100.0);
+            }
+            match (impact.critical_before, impact.critical_after) {
+                (Some(before), Some(after)) => narrate!("🧷 Critical relays {} -> {}", before, after),
+                (Some(before), None) => narrate!("🧷 Critical relays {} -> source cut off from target", before),
+                _ => {}
+            }
+            self.disaster_impacts.push(impact);
+            current_step_events.push(SimEvent::Disaster { step, destroyed: destroyed_count });
+            #[cfg(feature = "tokenomics")]
+            {
+                self.disaster_triggered = true;
+            }
+        }
+
+        // 1b. Churn: phones randomly drop offline and (maybe) come back
+        if self.config.churn_prob > 0.0 || self.config.recovery_prob > 0.0 {
+            for node in &mut self.nodes {
+                let id = node.id as usize;
+                if self.churned[id] {
+                    if self.node_rngs[id].random_bool(self.config.recovery_prob) {
+                        self.churned[id] = false;
+                        node.is_active = true;
+                        current_step_events.push(SimEvent::NodeRecovery { step, node_id: node.id });
+                    }
+                } else if node.is_active
+                    && node.node_type == NodeType::Smartphone
+                    && self.node_rngs[id].random_bool(self.config.churn_prob)
+                {
+                    self.churned[id] = true;
+                    node.is_active = false;
+                    death_causes.insert(node.id, DeathCause::Churn);
+                }
+            }
+        }
+
+        // 2. Oracle (Tokenomics)
+        #[cfg(feature = "tokenomics")]
+        if self.disaster_triggered && !self.oracle_paid.iter().all(|&paid| paid) && mode.has_tokenomics() {
+            self.run_oracle(step, &mut current_step_events);
+        }
+
+        // 2b. Target Watch: once the destination dies, the flow is over
+        if self.target_lost_step.is_none() && !self.nodes[target_node_id as usize].is_active {
+            narrate!("🪦 TARGET_LOST: node {} is down, flow stops counting.", target_node_id);
+            current_step_events.push(SimEvent::TargetLost { step, target_id: target_node_id });
+            self.target_lost_step = Some(step);
+        }
+
+        // 3. New Packet Generation
+        if step <= self.config.max_steps
+            && self.nodes[start_node_id as usize].is_active
+            && self.target_lost_step.is_none()
+        {
+            let count = packets_to_generate(self.config.traffic_pattern, step, &mut self.rng);
+            self.generated_packets += count;
+            let (min_size, max_size) = self.config.packet_size_range;
+            for _ in 0..count {
+                // Only draw when sizes vary, so fixed-size runs keep their random stream
+                let size_bytes = if min_size == max_size { min_size } else { self.rng.random_range(min_size..=max_size) };
+                let id = PacketId { flow: None, source: start_node_id, seq: self.next_seq }.to_string();
+                self.next_seq += 1;
+                if self.config.flood_suppression {
+                    self.seen[start_node_id as usize].insert(id.clone(), step);
+                }
+                let packet = Packet {
+                    id,
+                    history: vec![start_node_id],
+                    target_id: target_node_id,
+                    hops: 0,
+                    ttl: self.config.ttl,
+                    size_bytes,
+                    created_step: step,
+                    target_hint: self.nodes[target_node_id as usize].position,
+                    hint_step: step,
+                    held_since: None,
+                    carried: false,
+                    hop_steps: vec![step],
+                };
+                if self.config.retry_after.is_some() {
+                    self.outstanding.insert(packet.id.clone(), (packet.clone(), 0, step));
+                }
+                self.packet_queue.push_back(packet);
+            }
+
+            // 3b. Retries: same id and creation step (latency counts from the first send), fresh TTL
+            if let Some(after) = self.config.retry_after {
+                if let Some(decay) = self.config.importance_decay {
+                    // Stale messages aren't worth another send
+                    let floor = self.config.importance_floor;
+                    let before = self.outstanding.len();
+                    self.outstanding.retain(|_, (original, _, _)| original.importance(step, decay) >= floor);
+                    self.abandoned_messages += (before - self.outstanding.len()) as u32;
+                }
+                let max_retries = self.config.max_retries;
+                let hint = self.nodes[target_node_id as usize].position;
+                for (original, resent, last_sent) in self.outstanding.values_mut() {
+                    if *resent >= max_retries || step - *last_sent < after {
+                        continue;
+                    }
+                    *resent += 1;
+                    *last_sent = step;
+                    self.retries += 1;
+                    self.packet_queue.push_back(Packet { target_hint: hint, hint_step: step, hop_steps: vec![step], ..original.clone() });
+                }
+            }
+        }
+
+        let decay = self.config.fair_load_decay;
+        self.relay_load.iter_mut().for_each(|load| *load *= decay);
+
+        // 4. Energy: harvest first, then idle drain (only while the radio is on)
+        let mut step_spent = vec![0.0f32; self.nodes.len()]; // Per-node energy this step, for the budget
+        let duty_cycle = self.config.duty_cycle;
+        let awake: Vec<bool> = self.nodes.iter()
+            .map(|n| n.node_type == NodeType::BaseStation || radio_on(self.master_seed, n.id, step, duty_cycle))
+            .collect();
+        for node in &mut self.nodes {
+            node.harvest();
+            if node.is_active && awake[node.id as usize] {
+                node.consume_battery(self.config.cost_idle);
+                self.total_energy_consumed += self.config.cost_idle;
+                self.idle_energy += self.config.cost_idle;
+                step_spent[node.id as usize] += self.config.cost_idle;
+            }
+        }
+
+        // 4b. Control overhead: the neighbor discovery a smarter-than-flooding protocol
+        // needs. Every live, listening node sends one beacon that each live neighbor hears.
+        if let Some(interval) = self.config.hello_interval
+            && mode != SimMode::Flooding
+            && (step - 1) % interval == 0
+        {
+            let (tx_cost, rx_cost) = (self.config.tx_cost(HELLO_BYTES), self.config.rx_cost(HELLO_BYTES));
+            let up = |nodes: &[Node], id: u32| nodes[id as usize].is_active && awake[id as usize];
+            for id in 0..self.nodes.len() as u32 {
+                if !up(&self.nodes, id) { continue; }
+                let listeners: Vec<u32> = self.graph.neighbors(id).iter().copied().filter(|&peer| up(&self.nodes, peer)).collect();
+                self.nodes[id as usize].consume_battery(tx_cost);
+                step_spent[id as usize] += tx_cost;
+                self.control_energy += tx_cost;
+                self.total_energy_consumed += tx_cost;
+                for peer in listeners {
+                    self.nodes[peer as usize].consume_battery(rx_cost);
+                    step_spent[peer as usize] += rx_cost;
+                    self.control_energy += rx_cost;
+                    self.total_energy_consumed += rx_cost;
+                }
+            }
+        }
+
+        // 5. Packet Processing
+        // Windowed suppression: a node forgets a message once it received it more than W steps ago
+        if self.config.flood_suppression
+            && let Some(window) = self.config.suppression_window
+        {
+            for seen in &mut self.seen {
+                seen.retain(|_, &mut at| step - at <= window);
+            }
+        }
+        // Most important first (a stable sort: equals keep queue order), so budgets and buffers squeeze stale copies
+        if let Some(decay) = self.config.importance_decay {
+            self.packet_queue.make_contiguous()
+                .sort_by(|a, b| b.importance(step, decay).total_cmp(&a.importance(step, decay)));
+        }
+        self.trim_queue();
+        self.max_queue_depth = self.max_queue_depth.max(self.packet_queue.len());
+        let mut next_queue: VecDeque<Packet> = VecDeque::new();
+        let mut step_visited: BTreeMap<String, BTreeSet<u32>> = BTreeMap::new();
+        let mut transmissions: u32 = 0; // Mesh-wide this step, for the spectrum budget
+        let mut carrying = vec![0usize; self.nodes.len()]; // Store-carry-forward buffer use per node
+
+        // For visualization: track verified paths this step
+        let mut verified_packets: Vec<PacketLog> = Vec::new();
+        let mut timelines: Vec<MessageTimeline> = Vec::new();
+
+        // Interference: every node holding a packet transmits this step. The more
+        // of a node's neighbors are also on air, the shorter its effective range.
+        let mut effective_range: BTreeMap<u32, f64> = BTreeMap::new();
+        if self.config.interference {
+            let transmitters: BTreeSet<u32> = self.packet_queue.iter()
+                .map(|p| *p.history.last().unwrap())
+                .filter(|&id| id != target_node_id && self.nodes[id as usize].is_active)
+                .collect();
+            for &id in &transmitters {
+                let node = &self.nodes[id as usize];
+                let busy = node.peers.iter().filter(|p| transmitters.contains(p)).count();
+                let scale = (1.0 - self.config.interference_factor * busy as f64).max(INTERFERENCE_MIN_SCALE);
+                effective_range.insert(id, node.transmission_range * scale);
+            }
+        }
+
+        while let Some(mut packet) = self.packet_queue.pop_front() {
+            if self.config.backbone
+                && let Some(exit) = self.backbone_exit(&packet)
+            {
+                packet.history.push(exit); // No hop, no energy: the wire carries it
+                packet.hop_steps.push(step);
+                self.backbone_transfers += 1;
+            }
+            let current_node_id = *packet.history.last().unwrap();
+
+            let holder = &self.nodes[current_node_id as usize];
+            let at_target = current_node_id == target_node_id;
+            let via_backhaul = !at_target
+                && self.config.base_station_backhaul
+                && holder.node_type == NodeType::BaseStation
+                && holder.is_active;
+            if at_target || via_backhaul {
+                if self.target_lost_step.is_some() { // Arrived at a dead target
+                    self.message_fates.insert(packet.id, DropReason::DeadTarget);
+                    continue;
+                }
+                self.successful_packets += 1;
+                self.total_hops += packet.hops;
+                if packet.hops > self.config.ttl {
+                    self.beyond_ttl_deliveries += 1;
+                }
+                if via_backhaul {
+                    self.delivered_via_backhaul += 1;
+                } else {
+                    self.delivered_p2p += 1;
+                    if let Some(optimal) = self.optimal_hops().filter(|&h| h > 0) {
+                        self.stretch_sum += packet.hops as f64 / optimal as f64;
+                    }
+                }
+                if self.delivered_ids.insert(packet.id.clone()) {
+                    if !self.nodes[start_node_id as usize].is_active {
+                        self.drained_deliveries += 1;
+                    }
+                    if let Some((_, resent, _)) = self.outstanding.remove(&packet.id)
+                        && resent > 0
+                    {
+                        self.delivered_after_retry += 1;
+                    }
+                    self.latencies.push((step - packet.created_step) as u32);
+                    if packet.carried {
+                        self.carried_deliveries += 1;
+                    }
+                    self.delivered_bytes += packet.size_bytes as u64;
+                    self.delivery_steps.push(step);
+                    if packet.history.iter().any(|&id| self.nodes[id as usize].activation_step > 1) {
+                        self.late_bs_deliveries += 1;
+                    }
+                    timelines.push(MessageTimeline {
+                        id: packet.id.clone(),
+                        hops: packet.hop_steps.iter().copied().zip(packet.history.iter().copied()).collect(),
+                    });
+                } else {
+                    self.redundant_transmissions += 1;
+                }
+                self.delivered_paths.entry(packet.id.clone()).or_default().insert(packet.history.clone());
+                current_step_events.push(SimEvent::Delivery { step, packet_id: packet.id.clone(), hops: packet.hops });
+                if mode == SimMode::Adaptive {
+                    self.acknowledge(&packet.history);
+                }
+                // Everything between source and final holder was a relay
+                let relays = packet.history.len().saturating_sub(1);
+                for &id in packet.history.iter().take(relays).skip(1) {
+                    *self.path_appearances.entry(id).or_default() += 1;
+                    match self.nodes[id as usize].node_type {
+                        NodeType::Smartphone => self.relay_hops_smartphone += 1,
+                        NodeType::BaseStation => self.relay_hops_base_station += 1,
+                    }
+                }
+                verified_packets.push(PacketLog {
+                    id: packet.id.clone(),
+                    path: packet.history.clone()
+                });
+                continue;
+            }
+
+            // Infrastructure waypoint: a live base station gives the packet its full lifetime back
+            let holder = &self.nodes[current_node_id as usize];
+            if self.config.basestation_ttl_refresh
+                && holder.node_type == NodeType::BaseStation
+                && holder.is_active
+                && packet.ttl < self.config.ttl
+            {
+                packet.ttl = self.config.ttl;
+                self.ttl_refreshes += 1;
+            }
+
+            if !self.nodes[current_node_id as usize].is_active {
+                let id = current_node_id as usize;
+                let reason = self.dead_holder(current_node_id, step, was_active[id], death_causes.get(&current_node_id).copied());
+                self.message_fates.insert(packet.id, reason);
+                continue;
+            }
+            if packet.ttl == 0 {
+                self.message_fates.insert(packet.id, DropReason::Ttl);
+                continue;
+            }
+
+            // Duty cycle: a sleeping holder keeps the packet until its radio comes back on
+            if !awake[current_node_id as usize] {
+                self.sleep_deferrals += 1;
+                next_queue.push_back(packet);
+                continue;
+            }
+
+            // Free-riding: a relay may decline to spend battery on someone else's packet
+            let holder = &self.nodes[current_node_id as usize];
+            let cooperation = if holder.role.always_forwards() { 1.0 } else { holder.cooperation_prob };
+            if current_node_id != start_node_id && cooperation < 1.0 && !self.node_rngs[current_node_id as usize].random_bool(cooperation) {
+                self.refused_forwards += 1;
+                self.message_fates.insert(packet.id, DropReason::Refused);
+                continue;
+            }
+
+            // Self-preservation: a nearly flat phone keeps what's left for its owner
+            let holder = &self.nodes[current_node_id as usize];
+            if current_node_id != start_node_id
+                && holder.node_type == NodeType::Smartphone
+                && holder.battery_level < self.config.relay_cutoff_battery * holder.battery_capacity
+            {
+                self.cutoff_refusals += 1;
+                self.cutoff_ids.insert(packet.id.clone());
+                self.message_fates.insert(packet.id, DropReason::Refused);
+                continue;
+            }
+
+            // Energy budget: an exhausted holder keeps the packet for next step
+            if let Some(budget) = self.config.step_energy_budget
+                && step_spent[current_node_id as usize] + self.config.tx_cost(packet.size_bytes) > budget
+            {
+                self.deferred_packets += 1;
+                next_queue.push_back(packet);
+                continue;
+            }
+
+            // Spectrum budget: once the step's airtime is gone, holders wait in queue order
+            if self.config.global_tx_budget_per_step.is_some_and(|slots| transmissions >= slots) {
+                self.spectrum_deferrals += 1;
+                next_queue.push_back(packet);
+                continue;
+            }
+            transmissions += 1;
+
+            // TX Cost: a broadcast pays once up front, unicast pays per neighbor below
+            let tx_cost = self.config.tx_cost(packet.size_bytes);
+            let unicast = self.config.transmission_mode == TransmissionMode::Unicast;
+            if !unicast {
+                self.nodes[current_node_id as usize].consume_battery(tx_cost);
+                self.total_energy_consumed += tx_cost;
+                self.tx_energy += tx_cost;
+                *self.message_energy.entry(packet.id.clone()).or_default() += tx_cost;
+                step_spent[current_node_id as usize] += tx_cost;
+            }
+
+            let peers = self.nodes[current_node_id as usize].peers.clone();
+
+            // Positive progress: neighbors no closer to the target hint than the holder are off limits
+            let backward: BTreeSet<u32> = if self.config.forward_progress_only {
+                let metric = self.config.distance_metric;
+                let own = self.nodes[current_node_id as usize].distance_to_point(packet.target_hint, metric);
+                peers.iter()
+                    .copied()
+                    .filter(|&id| self.nodes[id as usize].distance_to_point(packet.target_hint, metric) >= own)
+                    .collect()
+            } else {
+                BTreeSet::new()
+            };
+
+            // Strategies like WeightedSplit and Adaptive pick their relay subset up front
+            let candidates: Vec<u32> = peers.iter()
+                .copied()
+                .filter(|id| !packet.history.contains(id) && self.nodes[*id as usize].is_active && awake[*id as usize])
+                .filter(|&id| self.nodes[id as usize].role != NodeRole::Endpoint || id == target_node_id)
+                .filter(|id| !backward.contains(id))
+                .collect();
+            let ctx = RoutingContext {
+                step,
+                config: &self.config,
+                nodes: &self.nodes,
+                link_scores: &self.link_scores,
+                relay_load: &self.relay_load,
+            };
+            let chosen = self.strategy.select_neighbors(
+                &ctx,
+                &self.nodes[current_node_id as usize],
+                &candidates,
+                &packet,
+                &mut self.node_rngs[current_node_id as usize],
+            );
+
+            let mut copies = 0;
+            let mut lost = false; // A copy went out but died on the way
+            let mut over_budget = false; // The holder ran out of step budget before reaching every neighbor
+            for neighbor_id in peers {
+                if packet.history.contains(&neighbor_id) { continue; } // No loops
+
+                let visited_set = step_visited.entry(packet.id.clone()).or_default();
+                if visited_set.contains(&neighbor_id) { continue; } // No duplicate sends in same step
+
+                let neighbor = &self.nodes[neighbor_id as usize];
+                if !neighbor.is_active || !awake[neighbor_id as usize] { continue; }
+                // Endpoint firmware only accepts traffic addressed to it
+                if neighbor.role == NodeRole::Endpoint && neighbor_id != target_node_id { continue; }
+                if backward.contains(&neighbor_id) {
+                    self.backward_skips += 1;
+                    continue;
+                }
+
+                let suppress = mode == SimMode::Flooding && self.config.flood_suppression;
+                if suppress && self.seen[neighbor_id as usize].contains_key(&packet.id) {
+                    self.suppressed_duplicates += 1;
+                    continue;
+                }
+
+                // --- ROUTING LOGIC ---
+                let should_forward = chosen.as_ref().is_none_or(|set| set.contains(&neighbor_id))
+                    && self.strategy.should_forward(
+                        &self.nodes[current_node_id as usize],
+                        neighbor,
+                        &packet,
+                        &mut self.node_rngs[current_node_id as usize],
+                    );
+
+                if should_forward {
+                    // Link quality: weak links cost the sender retries and may still drop the copy
+                    let weakness = 1.0 - self.graph.link_quality(current_node_id, neighbor_id).unwrap_or(1.0);
+                    let retry_cost = self.config.weak_link_cost * weakness as f32;
+
+                    // Energy budget: a copy the holder can't afford this step waits, with the rest, for the next one
+                    let cost = if unicast { tx_cost } else { 0.0 } + retry_cost;
+                    if let Some(budget) = self.config.step_energy_budget
+                        && cost > 0.0
+                        && step_spent[current_node_id as usize] + cost > budget
+                    {
+                        over_budget = true;
+                        break;
+                    }
+
+                    if unicast {
+                        self.nodes[current_node_id as usize].consume_battery(tx_cost);
+                        self.total_energy_consumed += tx_cost;
+                        self.tx_energy += tx_cost;
+                        *self.message_energy.entry(packet.id.clone()).or_default() += tx_cost;
+                        step_spent[current_node_id as usize] += tx_cost;
+                    }
+
+                    // Every try counts as a failure until an ACK says otherwise
+                    if mode == SimMode::Adaptive {
+                        let score = self.link_scores.entry((current_node_id, neighbor_id)).or_insert(ADAPTIVE_INITIAL_SCORE);
+                        *score *= 1.0 - ADAPTIVE_ALPHA;
+                    }
+
+                    if self.config.weak_link_cost > 0.0 {
+                        self.nodes[current_node_id as usize].consume_battery(retry_cost);
+                        self.total_energy_consumed += retry_cost;
+                        self.tx_energy += retry_cost;
+                        *self.message_energy.entry(packet.id.clone()).or_default() += retry_cost;
+                        step_spent[current_node_id as usize] += retry_cost;
+                    }
+                    // Interference: the copy went out but the neighbor is beyond this step's effective range
+                    if let Some(&range) = effective_range.get(&current_node_id)
+                        && self.nodes[current_node_id as usize].distance_to(&self.nodes[neighbor_id as usize], self.config.distance_metric) > range
+                    {
+                        self.interference_losses += 1;
+                        lost = true;
+                        continue;
+                    }
+                    if self.config.link_loss > 0.0
+                        && self.node_rngs[current_node_id as usize].random_bool(self.config.link_loss * weakness)
+                    {
+                        self.link_losses += 1;
+                        lost = true;
+                        continue;
+                    }
+
+                    let rx_cost = self.config.rx_cost(packet.size_bytes);
+                    self.nodes[neighbor_id as usize].consume_battery(rx_cost);
+                    self.total_energy_consumed += rx_cost;
+                    self.rx_energy += rx_cost;
+                    *self.message_energy.entry(packet.id.clone()).or_default() += rx_cost;
+                    step_spent[neighbor_id as usize] += rx_cost;
+                    self.forwards += 1;
+                    let mut reward = self.config.relay_reward(step);
+                    if self.config.progress_reward != 1.0 && self.makes_progress(&packet, current_node_id, neighbor_id) {
+                        reward *= self.config.progress_reward;
+                    }
+                    self.rewards_owed += reward;
+                    if current_node_id != start_node_id {
+                        self.nodes[current_node_id as usize].packets_relayed += 1;
+                        self.relay_load[current_node_id as usize] += 1.0;
+                    }
+
+                    // Token Reward (Mining). Rewards never feed back into forwarding,
+                    // so switching them off leaves routing untouched.
+                    #[cfg(feature = "tokenomics")]
+                    if mode.has_tokenomics() && self.config.relay_rewards {
+                        self.nodes[neighbor_id as usize].wallet.balance_token += reward;
+                        self.minted_per_step[step as usize - 1] += reward;
+                        self.premiums_usdc += self.config.premium_per_relay;
+                    }
+
+                    // Oscillation: far more hops than the shortest way to get here
+                    if let Some(optimal) = self.shortest_hops[neighbor_id as usize]
+                        && (packet.hops + 1) as f64 > self.config.oscillation_factor * optimal as f64
+                        && self.oscillating_ids.insert(packet.id.clone())
+                    {
+                        current_step_events.push(SimEvent::Oscillating { step, packet_id: packet.id.clone() });
+                    }
+
+                    let mut new_history = packet.history.clone();
+                    new_history.push(neighbor_id);
+                    let mut hop_steps = packet.hop_steps.clone();
+                    hop_steps.push(step + 1); // The neighbor works with it next step
+
+                    // A relay that hears the target's beacon sees where it is now. Links are
+                    // directional, so that's the target -> relay link, not relay -> target.
+                    let (target_hint, hint_step) = if self.graph.has_link(target_node_id, neighbor_id) {
+                        (self.nodes[target_node_id as usize].position, step)
+                    } else {
+                        (packet.target_hint, packet.hint_step)
+                    };
+
+                    next_queue.push_back(Packet {
+                        id: packet.id.clone(),
+                        history: new_history,
+                        target_id: packet.target_id,
+                        hops: packet.hops + 1,
+                        ttl: packet.ttl - 1,
+                        size_bytes: packet.size_bytes,
+                        created_step: packet.created_step,
+                        target_hint,
+                        hint_step,
+                        held_since: None,
+                        carried: packet.carried,
+                        hop_steps,
+                    });
+
+                    visited_set.insert(neighbor_id);
+                    if suppress {
+                        self.seen[neighbor_id as usize].insert(packet.id.clone(), step);
+                    }
+                    copies += 1;
+                }
+            }
+            if over_budget {
+                self.deferred_packets += 1;
+                next_queue.push_back(packet);
+                continue;
+            }
+            if copies == 0 {
+                // Store-carry-forward: keep it and try again next step, while there's room and patience
+                let holder = current_node_id as usize;
+                let since = packet.held_since.unwrap_or(step);
+                if let Some(buffer) = self.config.carry_buffer
+                    && self.nodes[holder].is_active
+                    && step - since < self.config.carry_max_hold
+                {
+                    if carrying[holder] < buffer {
+                        carrying[holder] += 1;
+                        if packet.held_since.is_none() {
+                            self.carried_packets += 1;
+                        }
+                        next_queue.push_back(Packet { held_since: Some(since), carried: true, ..packet });
+                        continue;
+                    }
+                    self.carry_overflow += 1;
+                }
+                self.message_fates.insert(packet.id, if lost { DropReason::Loss } else { DropReason::NoRoute });
+            }
+        }
+        self.packet_queue = next_queue;
+        self.trim_queue();
+
+        // 6. Casualties and connectivity
+        for (node, was) in self.nodes.iter().zip(was_active) {
+            if was && !node.is_active {
+                let cause = death_causes.get(&node.id).copied().unwrap_or(DeathCause::Battery);
+                match cause {
+                    DeathCause::Disaster => self.deaths_disaster += 1,
+                    DeathCause::Battery => self.deaths_battery += 1,
+                    DeathCause::Churn => self.deaths_churn += 1,
+                }
+                current_step_events.push(SimEvent::NodeDeath { step, node_id: node.id, cause });
+                self.deaths.insert(node.id, (cause, step));
+            }
+        }
+        let connected = hop_distances(&self.nodes, start_node_id)[target_node_id as usize].is_some();
+        if connected {
+            self.partitioned = false;
+            self.partitioned_since = None; // Healed (recovery, late station): not dead yet
+        } else if !self.partitioned {
+            self.partitioned_since = Some(step);
+            narrate!("✂️  PARTITION: source {} can no longer reach target {}", start_node_id, target_node_id);
+            current_step_events.push(SimEvent::Partition { step });
+            self.partitioned = true;
+        }
+
+        if let Some(cov) = battery_cov(&self.nodes) {
+            self.battery_cov.push((step, cov));
+        }
+        let active_nodes = self.nodes.iter().filter(|n| n.is_active).count();
+        self.active_nodes.push((step, active_nodes));
+
+        if matches!(self.config.traffic_pattern, TrafficPattern::Once { .. })
+            && self.packet_queue.is_empty()
+            && self.settled_step.is_none()
+        {
+            narrate!("🏁 Burst settled: nothing left in flight after step {}.", step);
+            self.settled_step = Some(step);
+        }
+
+        // Source watch: generation is over, whatever is queued still gets routed
+        let source_alive = self.nodes[start_node_id as usize].is_active;
+        if !source_alive && self.source_lost_step.is_none() {
+            narrate!("🔌 SOURCE_LOST: node {} is down, draining {} in flight.", start_node_id, self.packet_queue.len());
+            self.source_lost_step = Some(step);
+        }
+
+        StepReport {
+            step,
+            source_alive,
+            events: current_step_events,
+            delivered: verified_packets,
+            timelines,
+            active_nodes,
+            in_flight: self.packet_queue.len(),
+            total_energy: self.total_energy_consumed,
+        }
+    }
+}
+
+// Step to completion; per-step logs are only built when there's a `log_sink` to take them
+fn simulate(
+    mut sim: Simulation,
+    mut event_sink: Option<&mut dyn FnMut(&SimEvent)>,
+    mut step_sink: Option<&mut dyn FnMut(&StepReport)>,
+    mut log_sink: Option<&mut dyn FnMut(SimLog) -> Result<(), ExportError>>,
+) -> Result<(SimStats, RunTimings), ExportError> {
+    let stepping_start = Instant::now();
+
+    // Coverage cells only move when a base station comes up or goes down
+    let mut live_stations: Option<Vec<u32>> = None;
+    let mut cells: Vec<Option<u32>> = Vec::new();
+
+    while !sim.is_finished() {
+        let report = sim.step();
+        if let Some(sink) = event_sink.as_mut() {
+            for event in &report.events {
+                sink(event);
+            }
+        }
+        if let Some(sink) = step_sink.as_mut() {
+            sink(&report);
+        }
+
+        // SAVE LOGS (Only for Swarm mode usually, or we can save both. Let's save Swarm for v4 visualization)
+        if let Some(sink) = log_sink.as_mut() {
+             let stations: Vec<u32> = sim.state().nodes.iter()
+                 .filter(|n| n.node_type == NodeType::BaseStation && n.is_active)
+                 .map(|n| n.id)
+                 .collect();
+             if live_stations.as_ref() != Some(&stations) {
+                 cells = nearest_base_stations(sim.state().nodes, sim.config.distance_metric);
+                 live_stations = Some(stations);
+             }
+             let node_logs = sim.state().nodes.iter().zip(&cells).map(|(n, &cluster_id)| NodeLog {
+                 id: n.id,
+                 lat: n.lat,
+                 lon: n.lon,
+                 is_active: n.is_active,
+                 node_type: format!("{:?}", n.node_type),
+                 battery: n.battery_level,
+                 cluster_id,
+             }).collect();
+
+             sink(SimLog {
+                 step: report.step,
+                 nodes: node_logs,
+                 packets: report.delivered,
+                 in_flight: sim.state().packets.iter()
+                     .map(|p| PacketLog { id: p.id.clone(), path: p.history.clone() })
+                     .collect(),
+                 events: report.events.iter().filter_map(SimEvent::log_tag).collect(),
+                 bounds: Bounds::of_active(sim.state().nodes),
+                 battery_cov: battery_cov(sim.state().nodes),
+                 active_node_count: report.active_nodes,
+                 timelines: report.timelines,
+             })?;
+        }
+    }
+
+    let timings = RunTimings { adjacency: sim.adjacency_time, stepping: stepping_start.elapsed(), ..RunTimings::default() };
+
+    if sim.step > sim.config.max_steps {
+        narrate!("🕒 Drained in-flight packets for {} extra steps.", sim.step - sim.config.max_steps);
+    }
+
+    let stats = sim.stats();
+    #[cfg(feature = "tokenomics")]
+    if let Some(ratio) = stats.solvency_ratio() {
+        narrate!("🏦 Insurance pool: {:.0} USDC premiums vs {:.0} USDC paid (solvency {:.2})",
+            stats.premiums_usdc, stats.payouts_usdc, ratio);
+        if ratio < 1.0 {
+            narrate!("⚠️  INSOLVENT: payouts exceed the premium reserve by {:.0} USDC",
+                stats.payouts_usdc - stats.premiums_usdc);
+        }
+    }
+
+    Ok((stats, timings))
+}
+
+pub fn run_simulation(
+    mode: SimMode,
+    export_logs: Option<&LogExport>,
+    config: &SimConfig,
+    event_sink: Option<&mut dyn FnMut(&SimEvent)>,
+) -> Result<SimStats, ExportError> {
+    Ok(run_simulation_timed(mode, export_logs, config, event_sink, None)?.0)
+}
+
+// Same as run_simulation, plus where the wall-clock time went; `step_sink` sees every
+// step's report as it happens (e.g. for live telemetry)
+pub fn run_simulation_timed(
+    mode: SimMode,
+    export_logs: Option<&LogExport>,
+    config: &SimConfig,
+    event_sink: Option<&mut dyn FnMut(&SimEvent)>,
+    step_sink: Option<&mut dyn FnMut(&StepReport)>,
+) -> Result<(SimStats, RunTimings), ExportError> {
+    narrate!("\n▶️ RUNNING SIMULATION: {:?}", mode);
+    export_run(Simulation::new(mode, config), export_logs, event_sink, step_sink)
+}
+
+// Run `sim` to completion, writing its log to `export_logs` if given
+fn export_run(
+    sim: Simulation,
+    export_logs: Option<&LogExport>,
+    event_sink: Option<&mut dyn FnMut(&SimEvent)>,
+    step_sink: Option<&mut dyn FnMut(&StepReport)>,
+) -> Result<(SimStats, RunTimings), ExportError> {
+    let Some(export) = export_logs else {
+        return simulate(sim, event_sink, step_sink, None);
+    };
+    let config = sim.config.clone();
+
+    // JSONL goes out a line per step as the run goes (its write time lands in stepping)
+    if export.format == LogFormat::Jsonl {
+        let mut stream = JsonlWriter::create(&export.path, &config)?;
+        let result = simulate(sim, event_sink, step_sink, Some(&mut |log| stream.write_step(&log)))?;
+        narrate!("💾 Log streamed to '{}'", export.path);
+        return Ok(result);
+    }
+
+    let mut sim_logs: Vec<SimLog> = Vec::new();
+    let (stats, mut timings) = simulate(sim, event_sink, step_sink, Some(&mut |log| {
+        sim_logs.push(log);
+        Ok(())
+    }))?;
+
+    let serialization_start = Instant::now();
+    let log_file = SimLogFile { schema_version: SCHEMA_VERSION, config, steps: sim_logs };
+    export.format.write(&log_file, &export.path)?;
+    narrate!("💾 Log exported to '{}'", export.path);
+    timings.serialization = serialization_start.elapsed();
+
+    Ok((stats, timings))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::placed_nodes;
+
+    fn disaster_victims_for(seed: u64) -> Vec<u32> {
+        let config = SimConfig {
+            seed: Some(seed),
+            disaster_region: DisasterRegion::Fraction { fraction: 0.3 },
+            ..SimConfig::default()
+        };
+        let mut sim = Simulation::new(SimMode::Swarm, &config);
+        let mut victims = Vec::new();
+        while sim.state().step < DISASTER_STEP {
+            for event in sim.step().events {
+                if let SimEvent::NodeDeath { node_id, cause: DeathCause::Disaster, .. } = event {
+                    victims.push(node_id);
+                }
+            }
+        }
+        victims
+    }
+
+    #[test]
+    fn hardened_nodes_keep_power_and_charge_through_the_disaster() {
+        let mut config = SimConfig { seed: Some(6), ..SimConfig::default() };
+        let probe = Simulation::new(SimMode::Swarm, &config);
+        let exposed: Vec<u32> = probe.state().nodes.iter()
+            .filter(|n| config.disaster_region.covers(n.position))
+            .map(|n| n.id)
+            .take(3)
+            .collect();
+        assert_eq!(exposed.len(), 3);
+        config.hardened_nodes = exposed.iter().copied().collect();
+        let mut sim = Simulation::new(SimMode::Swarm, &config);
+        while sim.state().step < DISASTER_STEP - 1 {
+            sim.step();
+        }
+        let before: Vec<(bool, f32)> = exposed.iter()
+            .map(|&id| (sim.state().nodes[id as usize].is_active, sim.state().nodes[id as usize].battery_level))
+            .collect();
+        let report = sim.step();
+        assert!(report.events.iter().any(|e| matches!(e, SimEvent::NodeDeath { cause: DeathCause::Disaster, .. })));
+        for (&id, &(was_active, charge)) in exposed.iter().zip(&before) {
+            let node = &sim.state().nodes[id as usize];
+            assert_eq!(node.is_active, was_active, "hardened node {} changed state", id);
+            // Only the ordinary per-step spend, never the disaster's wipe to zero
+            if was_active {
+                assert!(node.battery_level > 0.0 && node.battery_level >= charge - 50.0);
+            }
+        }
+    }
+
+    #[test]
+    fn partitioned_run_reports_no_ratios_instead_of_nan() {
+        // Source and target far out of range of each other, and nothing costs energy
+        let config = SimConfig {
+            seed: Some(1),
+            node_count: 2,
+            cost_idle: 0.0,
+            cost_tx: 0.0,
+            cost_rx: 0.0,
+            ..SimConfig::default()
+        };
+        let nodes = placed_nodes(&config, &[(10.0, 190.0), (190.0, 190.0)], None);
+        let mut sim = Simulation::from_nodes(SimMode::Flooding, &config, nodes);
+        while !sim.is_finished() {
+            sim.step();
+        }
+        let stats = sim.stats();
+        assert_eq!((stats.success_packets, stats.total_energy), (0, 0.0));
+        assert_eq!(stats.deliveries_per_kj(), None);
+        assert_eq!(stats.energy_saved_vs(&stats), None);
+        assert_eq!(stats.battery_extension_vs(&stats), None);
+        assert_eq!(stats.network_death_step, Some(1)); // Never connected, never delivered
+    }
+
+    #[test]
+    fn fraction_disaster_is_reproducible_under_seed() {
+        let first = disaster_victims_for(42);
+        assert!(!first.is_empty());
+        assert_eq!(first, disaster_victims_for(42));
+    }
+
+    #[test]
+    fn seeded_runs_are_identical() {
+        // Exercise every RNG consumer: Poisson traffic, churn, scattered disaster, free-riding
+        let config = SimConfig {
+            seed: Some(7),
+            traffic_pattern: TrafficPattern::Poisson { lambda: 2.0 },
+            churn_prob: 0.02,
+            recovery_prob: 0.2,
+            cooperation_prob: 0.8,
+            disaster_region: DisasterRegion::Fraction { fraction: 0.4 },
+            ..SimConfig::default()
+        };
+        for mode in [SimMode::Flooding, SimMode::Swarm, SimMode::WeightedSplit { k: 2 }, SimMode::Adaptive] {
+            let (mut logs_a, mut logs_b) = (Vec::new(), Vec::new());
+            let (stats_a, _) = simulate(Simulation::new(mode, &config), None, None, Some(&mut |log| { logs_a.push(log); Ok(()) })).unwrap();
+            let (stats_b, _) = simulate(Simulation::new(mode, &config), None, None, Some(&mut |log| { logs_b.push(log); Ok(()) })).unwrap();
+            assert_eq!(stats_a, stats_b, "{:?} stats diverged", mode);
+            assert_eq!(logs_a, logs_b, "{:?} exported node states diverged", mode);
+        }
+    }
+
+    #[test]
+    fn exported_logs_read_back_unchanged() {
+        // The visualizer and --replay only ever see the file, so it must hold exactly what the run built.
+        // Phones 0 - 1 - 3 in a line deliver; station 2 comes up late in the south and dies in the disaster.
+        let config = SimConfig {
+            seed: Some(5),
+            node_count: 4,
+            target: TargetSelection::ById(3),
+            cost_idle: 0.0,
+            cost_tx: 0.0,
+            cost_rx: 0.0,
+            ..SimConfig::default()
+        };
+        let crafted = || {
+            let types = [NodeType::Smartphone, NodeType::Smartphone, NodeType::BaseStation, NodeType::Smartphone];
+            let mut nodes = placed_nodes(&config, &[(20.0, 150.0), (50.0, 150.0), (50.0, 40.0), (80.0, 150.0)], Some(&types));
+            nodes[2].activation_step = 10;
+            Simulation::from_nodes(SimMode::Flooding, &config, nodes)
+        };
+        let mut expected = Vec::new();
+        simulate(crafted(), None, None, Some(&mut |log| { expected.push(log); Ok(()) })).unwrap();
+        assert!(expected.iter().any(|log| !log.timelines.is_empty()) && expected.iter().any(|log| !log.events.is_empty()));
+        for format in [LogFormat::Json, LogFormat::Jsonl, LogFormat::MessagePack] {
+            let path = std::env::temp_dir().join(format!("roundtrip-{}.{}", std::process::id(), format.extension()));
+            let export = LogExport { format, path: path.to_str().unwrap().to_string() };
+            export_run(crafted(), Some(&export), None, None).unwrap();
+            let bytes = std::fs::read(&path).unwrap();
+            std::fs::remove_file(&path).unwrap();
+            let log_file = format.decode(&bytes).unwrap();
+            assert_eq!(log_file.schema_version, SCHEMA_VERSION);
+            assert_eq!(log_file.config, config, "{:?} config changed on the way back", format);
+            assert_eq!(log_file.steps, expected, "{:?} steps changed on the way back", format);
+        }
+    }
+
+    #[test]
+    fn step_reports_are_stable() {
+        // Flooding fans every packet out to all peers, so any order dependence
+        // in neighbor or visited-set iteration would show up in the delivered paths
+        let config = SimConfig { seed: Some(11), interference: true, ..SimConfig::default() };
+        let mut a = Simulation::new(SimMode::Flooding, &config);
+        let mut b = Simulation::new(SimMode::Flooding, &config);
+        assert_eq!(a.graph().connected_components(), b.graph().connected_components());
+        while !a.is_finished() {
+            let (report_a, report_b) = (a.step(), b.step());
+            assert_eq!(report_a.events, report_b.events, "events diverged at step {}", report_a.step);
+            assert_eq!(report_a.delivered, report_b.delivered, "paths diverged at step {}", report_a.step);
+            assert_eq!(report_a.in_flight, report_b.in_flight);
+        }
+    }
+}
@@ -6,16 +6,35 @@ def create_map():
     print("🗺️ Loading simulation logs...")
     try:
         with open('simulation_log.json', 'r') as f:
-            logs = json.load(f)
+            data = json.load(f)
     except FileNotFoundError:
         print("❌ simulation_log.json not found. Run 'cargo run' first.")
         return
 
-    # Center on Nice, France
-    center_lat, center_lon = 43.71, 7.26
+    # Schema v1+ wraps the steps with version/config; older logs are a bare list
+    config = {}
+    if isinstance(data, dict):
+        logs = data['steps']
+        config = data.get('config', {})
+        print(f"📄 Log schema v{data['schema_version']}")
+    else:
+        logs = data
+
+    # Grid -> lat/lon as the simulator projected it (Nice, France by default)
+    proj = config.get('projection', {'origin_lat': 43.70, 'origin_lon': 7.25, 'scale': 0.0001})
+    half_grid = config.get('grid_size', 200.0) / 2
+    center_lat = proj['origin_lat'] + half_grid * proj['scale']
+    center_lon = proj['origin_lon'] + half_grid * proj['scale']
+    impact_lat = proj['origin_lat'] + half_grid / 2 * proj['scale'] # Middle of the southern zone
     # Premium Dark Mode Tiles
     m = folium.Map(location=[center_lat, center_lon], zoom_start=14, tiles='cartodbdark_matter')
 
+    # Schema v2+: zoom to where the mesh actually is
+    first_bounds = logs[0].get('bounds') if logs and isinstance(logs[0], dict) else None
+    if first_bounds:
+        m.fit_bounds([[first_bounds['min_lat'], first_bounds['min_lon']],
+                      [first_bounds['max_lat'], first_bounds['max_lon']]])
+
     features = []
 
     # 🎨 Unicorn Neon Color Palette
@@ -24,6 +43,7 @@ def create_map():
     COLOR_LOW_BAT = '#EF476F'     # Neon Red/Pink
     COLOR_DEAD = '#333333'        # Dark Gray
     COLOR_PACKET = '#118AB2'      # Neon Blue
+    COLOR_IN_FLIGHT = '#8ECAE6'   # Pale Blue
     
     # Event Colors
     COLOR_DISASTER = '#D00000'    # Deep Red
@@ -76,6 +96,7 @@ def create_map():
                         'radius': radius
                     },
                     'popup': f"Node {node['id']}<br>Type: {node['node_type']}<br>Bat: {node['battery']:.1f}"
+                             f"<br>Cell: BS {node.get('cluster_id')}"
                 }
             })
 
@@ -105,6 +126,28 @@ def create_map():
                     }
                 })
 
+        # 2b. IN FLIGHT (schema v4+): partial paths, so packets visibly hop step by step
+        for packet in step_log.get('in_flight', []):
+            path_coords = [[node_pos[nid][1], node_pos[nid][0]] for nid in packet['path'] if nid in node_pos]
+            if len(path_coords) > 1:
+                features.append({
+                    'type': 'Feature',
+                    'geometry': {
+                        'type': 'LineString',
+                        'coordinates': path_coords,
+                    },
+                    'properties': {
+                        'time': time_str,
+                        'style': {
+                            'color': COLOR_IN_FLIGHT,
+                            'weight': 1,
+                            'opacity': 0.5,
+                            'dashArray': '4',
+                        },
+                        'popup': f"In flight: {packet['id']} ({len(path_coords)-1} hops so far)"
+                    }
+                })
+
         # 3. EVENTS (Visualizing the Story)
         for event in step_log.get('events', []):
             if event == "DISASTER_START":
@@ -113,7 +156,7 @@ def create_map():
                     'type': 'Feature',
                     'geometry': {
                         'type': 'Point',
-                        'coordinates': [7.26, 43.705], # Approximate Center of Impact
+                        'coordinates': [center_lon, impact_lat], # Approximate Center of Impact
                     },
                     'properties': {
                         'time': time_str,
@@ -133,7 +176,7 @@ def create_map():
                     'type': 'Feature',
                     'geometry': {
                         'type': 'Point',
-                        'coordinates': [7.26, 43.71], # Center map
+                        'coordinates': [center_lon, center_lat], # Center map
                     },
                     'properties': {
                         'time': time_str,
@@ -0,0 +1,42 @@
+use resilient_mesh_sim::{run_simulation, LogExport, LogFormat, SimConfig, SimMode, RNG_BACKEND};
+
+// Regression guard: a fixed seeded run of every mode must reproduce the committed
+// fingerprints exactly. When routing or energy logic changes on purpose, rerun with
+// UPDATE_GOLDEN=1 and commit the new file alongside the change.
+const MODES: [SimMode; 4] = [SimMode::Flooding, SimMode::Swarm, SimMode::WeightedSplit { k: 2 }, SimMode::Adaptive];
+
+// FNV-1a: unlike DefaultHasher, stable across Rust releases
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &b| (hash ^ b as u64).wrapping_mul(0x0100_0000_01b3))
+}
+
+// Stats differ by RNG backend, and the tokenomics feature adds fields to SimStats
+fn golden_path() -> String {
+    let features = if cfg!(feature = "tokenomics") { "tokenomics" } else { "plain" };
+    format!("{}/tests/golden/{}-{}.txt", env!("CARGO_MANIFEST_DIR"), RNG_BACKEND, features)
+}
+
+fn fingerprint(index: usize, mode: SimMode) -> String {
+    let config = SimConfig { seed: Some(2), node_count: 60, max_steps: 40, ..SimConfig::default() };
+    let path = std::env::temp_dir().join(format!("golden-{}-{}.json", std::process::id(), index));
+    let export = LogExport { format: LogFormat::Json, path: path.to_str().unwrap().to_string() };
+    let stats = run_simulation(mode, Some(&export), &config, None).unwrap();
+    let log = std::fs::read(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    format!("{:?}: generated={} delivered={} energy={:.3} stats={:016x} log={:016x}",
+        mode, stats.generated_packets, stats.latencies.len(), stats.total_energy,
+        fnv1a(format!("{:?}", stats).as_bytes()), fnv1a(&log))
+}
+
+#[test]
+fn seeded_runs_match_the_golden_fingerprints() {
+    let actual: String = MODES.iter().enumerate().map(|(index, &mode)| fingerprint(index, mode) + "\n").collect();
+    let path = golden_path();
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::write(&path, &actual).unwrap();
+        return;
+    }
+    let expected = std::fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("{}: {} (run with UPDATE_GOLDEN=1 to create it)", path, e));
+    assert_eq!(actual, expected, "seeded output changed; if intended, rerun with UPDATE_GOLDEN=1");
+}
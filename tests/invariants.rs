@@ -0,0 +1,40 @@
+use resilient_mesh_sim::node::placed_nodes;
+use resilient_mesh_sim::{Node, SimConfig, SimMode, Simulation};
+use std::collections::BTreeSet;
+
+// 3x3 lattice of phones 25 apart with range 40: every square has both
+// diagonals, so the mesh is full of cycles for packets to loop around
+fn lattice(config: &SimConfig) -> Vec<Node> {
+    let positions: Vec<(f64, f64)> = (0..9).map(|id| (100.0 + 25.0 * (id % 3) as f64, 150.0 + 25.0 * (id / 3) as f64)).collect();
+    placed_nodes(config, &positions, None).into_iter().map(|mut node| {
+        node.cooperation_prob = 1.0;
+        node
+    }).collect()
+}
+
+fn assert_no_repeats(path: &[u32], step: i32) {
+    let unique: BTreeSet<u32> = path.iter().copied().collect();
+    assert_eq!(unique.len(), path.len(), "step {}: path {:?} revisits a node", step, path);
+}
+
+#[test]
+fn packets_never_loop_or_fork_twice_to_the_same_neighbor() {
+    let config = SimConfig { seed: Some(2), node_count: 9, ttl: 20, ..SimConfig::default() };
+    for mode in [SimMode::Flooding, SimMode::Swarm, SimMode::WeightedSplit { k: 2 }, SimMode::Adaptive] {
+        let mut sim = Simulation::from_nodes(mode, &config, lattice(&config));
+        while !sim.is_finished() {
+            let report = sim.step();
+            for delivered in &report.delivered {
+                assert_no_repeats(&delivered.path, report.step);
+            }
+            // Each queued copy is one forward made this step: (message, receiver) must be unique
+            let mut forwards = BTreeSet::new();
+            for packet in sim.state().packets {
+                assert_no_repeats(&packet.history, report.step);
+                let receiver = *packet.history.last().unwrap();
+                assert!(forwards.insert((packet.id.clone(), receiver)),
+                    "{:?} step {}: {} sent to node {} twice", mode, report.step, packet.id, receiver);
+            }
+        }
+    }
+}
@@ -0,0 +1,42 @@
+#![cfg(feature = "tokenomics")]
+
+use resilient_mesh_sim::config::{BATTERY_FULL_SMARTPHONE, DISASTER_STEP, DISASTER_ZONE_Y, INSURANCE_PAYOUT};
+use resilient_mesh_sim::node::placed_nodes;
+use resilient_mesh_sim::{Node, SimConfig, SimEvent, SimMode, Simulation};
+
+// Two rows of smartphones 30 apart: ids 0-2 north of the disaster line, 3-5 south of it
+fn crafted_nodes(config: &SimConfig) -> Vec<Node> {
+    let positions = [(20.0, 150.0), (50.0, 150.0), (80.0, 150.0), (20.0, 40.0), (50.0, 40.0), (80.0, 40.0)];
+    placed_nodes(config, &positions, None).into_iter().map(|mut node| {
+        node.battery_level = BATTERY_FULL_SMARTPHONE;
+        node.battery_capacity = BATTERY_FULL_SMARTPHONE;
+        node.harvest_rate = 0.0;
+        node
+    }).collect()
+}
+
+#[test]
+fn oracle_pays_the_south_exactly_once() {
+    let config = SimConfig { seed: Some(1), node_count: 6, ..SimConfig::default() };
+    let mut sim = Simulation::from_nodes(SimMode::Swarm, &config, crafted_nodes(&config));
+
+    let mut payouts = Vec::new();
+    while !sim.is_finished() {
+        let report = sim.step();
+        assert_eq!(sim.oracle_alert_sent(), report.step >= DISASTER_STEP, "step {}", report.step);
+        payouts.extend(report.events.into_iter().filter(|e| matches!(e, SimEvent::OraclePayout { .. })));
+    }
+
+    assert_eq!(payouts, vec![SimEvent::OraclePayout { step: DISASTER_STEP, recipients: 3, amount_usdc: INSURANCE_PAYOUT }]);
+    for node in sim.state().nodes {
+        let expected = if node.position.1 < DISASTER_ZONE_Y { INSURANCE_PAYOUT } else { 0.0 };
+        assert_eq!(node.wallet.balance_usdc, expected, "node {}", node.id);
+    }
+
+    // The exported end state is exactly what the nodes hold
+    let wallets = sim.stats().wallets;
+    assert_eq!(wallets.len(), sim.state().nodes.len());
+    for (log, node) in wallets.iter().zip(sim.state().nodes) {
+        assert_eq!((log.node_id, &log.wallet), (node.id, &node.wallet));
+    }
+}
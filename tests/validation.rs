@@ -0,0 +1,17 @@
+use resilient_mesh_sim::{ConfigError, SimConfig, SimMode, Simulation, TargetSelection};
+
+#[test]
+fn target_on_the_source_node_is_rejected() {
+    let config = SimConfig { target: TargetSelection::ById(0), ..SimConfig::default() };
+    assert_eq!(config.validate(), Err(ConfigError::TargetIsSource(0)));
+    // Every other fixed target, and the selectors that can't land on the source, stay valid
+    for target in [TargetSelection::ById(1), TargetSelection::LastNode, TargetSelection::NearestBaseStation, TargetSelection::RandomPair] {
+        assert_eq!(SimConfig { target, ..SimConfig::default() }.validate(), Ok(()));
+    }
+}
+
+#[test]
+fn unvalidated_self_targeting_config_refuses_to_run() {
+    let config = SimConfig { seed: Some(1), target: TargetSelection::ById(0), ..SimConfig::default() };
+    assert_eq!(Simulation::try_new(SimMode::Flooding, &config).err(), Some(ConfigError::TargetIsSource(0)));
+}